@@ -0,0 +1,147 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use futures::future::try_join_all;
+use mongodb::bson::doc;
+use mongodb::sync::Collection;
+use web3::types::{Address, Block, BlockId, BlockNumber, H256, U256, U64};
+use web3::Web3;
+
+use crate::importers::blocks;
+use crate::scanner::Transport;
+
+/// The parts of a block header the importers use.
+#[derive(Clone, Debug)]
+pub struct Header {
+    pub number: U64,
+    pub hash: H256,
+    pub parent_hash: H256,
+    /// Block timestamp in seconds.
+    pub timestamp: U256,
+    pub miner: Address,
+    pub gas_used: U256,
+    pub gas_limit: U256,
+    pub size: Option<U256>,
+    pub transaction_count: usize,
+}
+
+impl<TX> From<&Block<TX>> for Header {
+    fn from(block: &Block<TX>) -> Header {
+        Header {
+            number: block.number.unwrap_or_default(),
+            hash: block.hash.unwrap_or_default(),
+            parent_hash: block.parent_hash,
+            timestamp: block.timestamp,
+            miner: block.author,
+            gas_used: block.gas_used,
+            gas_limit: block.gas_limit,
+            size: block.size,
+            transaction_count: block.transactions.len(),
+        }
+    }
+}
+
+impl Header {
+    /// Header stored by the blocks importer, `None` if a hash does not parse.
+    fn from_stored(block: &blocks::Block) -> Option<Header> {
+        Some(Header {
+            number: U64::from(block.block),
+            hash: block.hash.parse().ok()?,
+            parent_hash: block.parent_hash.parse().ok()?,
+            timestamp: U256::from(block.created_at.timestamp_millis() / 1000),
+            miner: block.miner.parse().ok()?,
+            gas_used: U256::from(block.gas_used),
+            gas_limit: U256::from(block.gas_limit),
+            size: block.size.map(U256::from),
+            transaction_count: block.transaction_count as usize,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    headers: HashMap<U64, Header>,
+    /// Insertion order, the oldest entry is evicted first.
+    order: VecDeque<U64>,
+}
+
+/// Bounded cache of block headers, so blocks shared by many logs or importers are fetched once.
+/// Misses are looked up in the collection of the blocks importer if one is set, then fetched
+/// from the node.
+pub struct HeaderCache {
+    web3: Web3<Transport>,
+    capacity: usize,
+    entries: Mutex<Entries>,
+    blocks: Option<Collection<blocks::Block>>,
+}
+
+impl HeaderCache {
+    pub fn new(web3: Web3<Transport>, capacity: usize, blocks: Option<Collection<blocks::Block>>) -> HeaderCache {
+        HeaderCache { web3, capacity, entries: Mutex::default(), blocks }
+    }
+
+    pub fn insert(&self, header: Header) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.headers.insert(header.number, header.clone()).is_none() {
+            entries.order.push_back(header.number);
+        }
+        while entries.order.len() > self.capacity {
+            if let Some(number) = entries.order.pop_front() {
+                entries.headers.remove(&number);
+            }
+        }
+    }
+
+    /// Drops all headers after `block`, once a chain reorganisation replaced them.
+    pub fn forget_after(&self, block: U64) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.headers.retain(|number, _| *number <= block);
+        entries.order.retain(|number| *number <= block);
+    }
+
+    /// Headers of `numbers`, in the same order.
+    pub async fn headers(&self, numbers: &[U64]) -> web3::Result<Vec<Header>> {
+        let mut found: HashMap<U64, Header> = {
+            let entries = self.entries.lock().unwrap();
+            numbers.iter().filter_map(|number| entries.headers.get(number).cloned()).map(|header| (header.number, header)).collect()
+        };
+
+        let missing: Vec<U64> = numbers.iter().filter(|number| !found.contains_key(number)).copied().collect();
+        for header in self.stored(&missing) {
+            found.insert(header.number, header.clone());
+            self.insert(header);
+        }
+
+        let missing: Vec<U64> = numbers.iter().filter(|number| !found.contains_key(number)).copied().collect();
+        let fetched = try_join_all(missing.iter().map(|number| self.web3.eth().block(BlockId::Number(BlockNumber::from(*number))))).await?;
+        for (number, block) in missing.into_iter().zip(fetched) {
+            let header = Header::from(&block.ok_or_else(|| web3::Error::InvalidResponse(format!("Block {} not found", number)))?);
+            found.insert(number, header.clone());
+            self.insert(header);
+        }
+
+        Ok(numbers.iter().filter_map(|number| found.get(number).cloned()).collect())
+    }
+
+    /// Headers of `numbers` found in the blocks collection. Failed lookups only cost the RPC calls
+    /// they should have saved, so they are logged and otherwise ignored.
+    fn stored(&self, numbers: &[U64]) -> Vec<Header> {
+        let collection = match &self.blocks {
+            Some(collection) if !numbers.is_empty() => collection,
+            _ => return vec![]
+        };
+        let numbers: Vec<i64> = numbers.iter().map(|number| number.as_u64() as i64).collect();
+        let blocks = collection.find(doc! {"block": {"$in": numbers}}, None)
+            .and_then(|cursor| cursor.collect::<mongodb::error::Result<Vec<blocks::Block>>>());
+        match blocks {
+            Ok(blocks) => blocks.iter().filter_map(Header::from_stored).collect(),
+            Err(error) => {
+                println!("Reading cached headers failed: {}", error);
+                vec![]
+            }
+        }
+    }
+}
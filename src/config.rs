@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::scanner::ScannerOptions;
+use crate::tools::database::Options;
+use crate::transport::TransportOptions;
+
+/// Settings shared by all importers, loaded from a TOML file. Every key is optional; see
+/// `config.example.toml` for the full layout.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub web3: Web3Config,
+    pub mongodb: MongoDbConfig,
+    pub scanner: ScannerConfig,
+    pub contracts: ContractsConfig,
+    /// Per-importer settings, keyed by the importer's service name, e.g. `axie-transfers`.
+    pub importers: HashMap<String, ImporterConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Web3Config {
+    pub hostnames: Vec<String>,
+    /// Used for hosts without ws:// or http:// scheme.
+    pub provider_type: String,
+    pub rpc_retries: u32,
+    /// Delay before the first retry in milliseconds.
+    pub rpc_backoff: u64,
+    pub max_lag: u64,
+}
+
+impl Default for Web3Config {
+    fn default() -> Self {
+        Web3Config {
+            hostnames: vec!["ws://localhost:8546".to_string()],
+            provider_type: "ws".to_string(),
+            rpc_retries: 5,
+            rpc_backoff: 500,
+            max_lag: 10,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MongoDbConfig {
+    pub uri: String,
+    pub name: String,
+}
+
+impl Default for MongoDbConfig {
+    fn default() -> Self {
+        MongoDbConfig {
+            uri: "mongodb://127.0.0.1:27017".to_string(),
+            name: "ronin".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScannerConfig {
+    pub end_block: u32,
+    pub max_window: u64,
+    pub concurrency: usize,
+    pub confirmations: u64,
+    pub follow: bool,
+    /// Number of block headers kept in memory.
+    pub header_cache: usize,
+    /// Look up headers missing from the cache in the collection of the blocks importer before
+    /// asking the node.
+    pub headers_from_db: bool,
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        ScannerConfig {
+            end_block: 0,
+            max_window: 10000,
+            concurrency: 4,
+            confirmations: 0,
+            follow: false,
+            header_cache: 10000,
+            headers_from_db: false,
+        }
+    }
+}
+
+/// Where the token contract registry is loaded from. Without a file or collection the built-in
+/// registry is used.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContractsConfig {
+    /// TOML or JSON file (by extension) with a `contracts` list, see `contracts.example.toml`.
+    pub file: Option<PathBuf>,
+    /// Collection in the importer's database with one document per contract.
+    pub collection: Option<String>,
+}
+
+/// Settings of a single importer. Unset values fall back to the importer's defaults, then to the
+/// global settings.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImporterConfig {
+    /// Overrides `mongodb.name` for this importer.
+    pub database: Option<String>,
+    pub collection: Option<String>,
+    pub start_block: Option<u32>,
+    pub batch_size: Option<usize>,
+    /// Overrides `scanner.confirmations` for this importer.
+    pub confirmations: Option<u64>,
+    /// Whether the transactions importer stores the receipt fields.
+    pub receipts: Option<bool>,
+}
+
+impl ImporterConfig {
+    /// Fills every unset value from `defaults`.
+    fn or(self, defaults: ImporterConfig) -> ImporterConfig {
+        ImporterConfig {
+            database: self.database.or(defaults.database),
+            collection: self.collection.or(defaults.collection),
+            start_block: self.start_block.or(defaults.start_block),
+            batch_size: self.batch_size.or(defaults.batch_size),
+            confirmations: self.confirmations.or(defaults.confirmations),
+            receipts: self.receipts.or(defaults.receipts),
+        }
+    }
+}
+
+/// Built-in settings of the importers, used for everything the config file leaves out.
+fn importer_defaults(name: &str) -> ImporterConfig {
+    let (collection, start_block) = match name {
+        "blocks" => ("blocks", 0),
+        "transactions" => ("transactions", 1),
+        "axie-transfers" => ("axietransfers", 2678592),
+        "axie-sales" => ("axiesales", 2678592),
+        "erc-transfer" => ("tokentransfers", 1),
+        "battle-logs" => ("pvpbattlelogs", 0),
+        "block-stats" => {
+            return ImporterConfig {
+                database: Some("roninstatistics".to_string()),
+                collection: Some("blocks".to_string()),
+                start_block: Some(0),
+                batch_size: Some(10000),
+                confirmations: Some(50),
+                ..Default::default()
+            };
+        }
+        _ => (name, 1)
+    };
+    ImporterConfig {
+        collection: Some(collection.to_string()),
+        start_block: Some(start_block),
+        ..Default::default()
+    }
+}
+
+impl Config {
+    /// Reads the config file at `path`, or returns the built-in defaults if there is none.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(Config::default())
+        };
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| Error::Config(format!("Failed to read {}: {}", path.display(), error)))?;
+        toml::from_str(&contents)
+            .map_err(|error| Error::Config(format!("Invalid config file {}: {}", path.display(), error)))
+    }
+
+    /// Section of importer `name`, completed with the importer's defaults.
+    pub fn importer(&self, name: &str) -> ImporterConfig {
+        let section = self.importers.get(name).cloned().unwrap_or_default();
+        section.or(importer_defaults(name))
+    }
+
+    pub fn importer_mut(&mut self, name: &str) -> &mut ImporterConfig {
+        self.importers.entry(name.to_string()).or_default()
+    }
+
+    /// Collection importer `name` writes to.
+    pub fn collection(&self, name: &str) -> String {
+        self.importer(name).collection.unwrap_or_else(|| name.to_string())
+    }
+
+    pub fn database_options(&self, name: &str) -> Options {
+        Options {
+            client_uri: self.mongodb.uri.clone(),
+            database: self.importer(name).database.unwrap_or_else(|| self.mongodb.name.clone()),
+        }
+    }
+
+    pub fn transport_options(&self) -> TransportOptions {
+        TransportOptions {
+            retries: self.web3.rpc_retries,
+            backoff: Duration::from_millis(self.web3.rpc_backoff),
+            max_lag: self.web3.max_lag,
+            ..Default::default()
+        }
+    }
+
+    pub fn scanner_options(&self, name: &str) -> ScannerOptions {
+        let importer = self.importer(name);
+        let defaults = ScannerOptions::default();
+        ScannerOptions {
+            start_block: importer.start_block.unwrap_or(defaults.start_block),
+            end_block: self.scanner.end_block,
+            confirmations: importer.confirmations.unwrap_or(self.scanner.confirmations),
+            batch_size: importer.batch_size.unwrap_or(defaults.batch_size),
+            max_window: self.scanner.max_window,
+            concurrency: self.scanner.concurrency,
+            follow: self.scanner.follow,
+            ..defaults
+        }
+    }
+}
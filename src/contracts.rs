@@ -1,5 +1,5 @@
 pub mod database {
-    use mongodb::bson::DateTime;
+    use mongodb::bson::{DateTime, Decimal128};
     use crate::contracts::contracts::ContractType;
     use serde::{Serialize, Deserialize};
 
@@ -9,11 +9,18 @@ pub mod database {
         pub to: String,
         pub token: String,
         pub value_or_token_id: String,
+        /// Transferred amount in whole tokens, ERC20 only.
+        pub amount: Option<Decimal128>,
+        /// Symbol of the token, see [`Contract::name`](crate::contracts::contracts::Contract::name).
+        pub symbol: String,
         pub created_at: DateTime,
         pub block: u64,
         pub transaction_id: String,
         pub erc: ContractType,
         pub log_index: String,
+        /// Number of tokens of id `value_or_token_id` transferred, ERC1155 only.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub quantity: Option<String>,
         pub log_id: String
     }
 
@@ -24,96 +31,255 @@ pub mod database {
                 to: "0x0000000000000000000000000000000000000000".to_string(),
                 token: "0x0000000000000000000000000000000000000000".to_string(),
                 value_or_token_id: "0".to_string(),
+                amount: None,
+                symbol: String::new(),
                 created_at: DateTime::from_millis(chrono::Utc::now().timestamp() * 1000),
                 block: 0u64,
                 transaction_id: "0x0000000000000000000000000000000000000000000000000000000000000000".to_string(),
                 erc: ContractType::Unknown,
                 log_index: "0".to_string(),
+                quantity: None,
                 log_id: "0x0".to_string()
             }
         }
     }
 }
 
+#[allow(clippy::module_inception)]
 pub mod contracts {
     use std::collections::HashMap;
+    use mongodb::bson::{Bson, Decimal128};
     use serde::{Serialize, Deserialize};
+    use web3::types::{Address, U256};
 
-    pub type ContractList = HashMap<&'static str, Contract>;
+    use crate::config::ContractsConfig;
+    use crate::error::{Error, Result};
+    use crate::tools::database::MongoDb;
 
-    #[derive(Serialize, Deserialize)]
+    /// Registered contracts keyed by their lowercase address.
+    pub type ContractList = HashMap<String, Contract>;
+
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
     pub enum ContractType {
         ERC20,
         ERC721,
+        ERC1155,
         Unknown
     }
 
-    #[derive(Serialize, Deserialize)]
+    #[derive(Serialize, Deserialize, Clone, Debug)]
     pub struct Contract {
-        pub name: &'static str,
+        /// Token symbol, e.g. `WETH`.
+        pub name: String,
+        #[serde(default)]
+        pub decimals: usize,
+        pub erc: ContractType,
+        /// First block with transfers of this contract, usually its deployment block.
+        #[serde(default)]
+        pub start_block: u32,
+    }
+
+    /// Entry of a registry file or of the contracts collection, with the fields of [`Contract`].
+    /// Unknown keys are rejected, so a typo like `startblock` fails the load instead of falling
+    /// back to the default.
+    #[derive(Serialize, Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    pub struct RegisteredContract {
+        pub address: String,
+        pub name: String,
+        #[serde(default)]
         pub decimals: usize,
         pub erc: ContractType,
+        #[serde(default)]
+        pub start_block: u32,
+        /// Document id, for entries read from the contracts collection.
+        #[serde(default, rename = "_id", skip_serializing_if = "Option::is_none")]
+        pub id: Option<Bson>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct RegistryFile {
+        contracts: Vec<RegisteredContract>,
+    }
+
+    impl Contract {
+        /// `value` in whole tokens, e.g. 1.5 WETH for 1500000000000000000. `None` for NFTs, whose
+        /// values are token ids.
+        pub fn amount(&self, value: U256) -> Option<Decimal128> {
+            match self.erc {
+                ContractType::ERC20 => Some(normalize(value, self.decimals)),
+                _ => None
+            }
+        }
+    }
+
+    /// `value / 10^decimals` as a Decimal128. Values with more than 34 significant digits are
+    /// truncated to the 34 most significant ones.
+    pub fn normalize(value: U256, decimals: usize) -> Decimal128 {
+        let mut digits = value.to_string();
+        let mut exponent = if value.is_zero() { 0 } else { -(decimals as i64) };
+        while digits.len() > 1 && (digits.len() > 34 || (exponent < 0 && digits.ends_with('0'))) {
+            digits.pop();
+            exponent += 1;
+        }
+        format!("{}E{}", digits, exponent).parse().unwrap_or_else(|_| Decimal128::from_bytes([0; 16]))
+    }
+
+    /// Builds the registry from `entries`, normalizing their addresses.
+    pub fn from_entries(entries: Vec<RegisteredContract>) -> Result<ContractList> {
+        let mut contracts = ContractList::new();
+        for RegisteredContract { address, name, decimals, erc, start_block, .. } in entries {
+            let parsed: Address = address.parse().map_err(|_| Error::Config(format!("Invalid contract address {}", address)))?;
+            contracts.insert(format!("{:?}", parsed), Contract { name, decimals, erc, start_block });
+        }
+        Ok(contracts)
+    }
+
+    /// Loads the registry from the file or collection configured in `[contracts]`, or returns the
+    /// built-in registry if neither is set. The collection is read from `db`.
+    pub fn load(config: &ContractsConfig, db: &MongoDb) -> Result<ContractList> {
+        if let Some(path) = &config.file {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|error| Error::Config(format!("Failed to read {}: {}", path.display(), error)))?;
+            let file: RegistryFile = if path.extension().is_some_and(|extension| extension == "json") {
+                serde_json::from_str(&contents).map_err(|error| error.to_string())
+            } else {
+                toml::from_str(&contents).map_err(|error| error.to_string())
+            }.map_err(|error| Error::Config(format!("Invalid contract registry {}: {}", path.display(), error)))?;
+            return from_entries(file.contracts);
+        }
+
+        if let Some(name) = &config.collection {
+            let entries = db.database.collection::<RegisteredContract>(name)
+                .find(None, None)?
+                .collect::<mongodb::error::Result<Vec<_>>>()?;
+            if entries.is_empty() {
+                return Err(Error::Config(format!("The contract registry {} is empty", name)));
+            }
+            return from_entries(entries);
+        }
+
+        Ok(default())
     }
 
+    /// Addresses of all registered contracts, e.g. for a combined logs filter.
+    pub fn addresses(contracts: &ContractList) -> Vec<Address> {
+        contracts.keys().map(|address| address.parse().unwrap()).collect()
+    }
+
+    /// Looks up the registered contract that emitted a log.
+    pub fn find<'a>(contracts: &'a ContractList, address: &Address) -> Option<&'a Contract> {
+        contracts.get(&format!("{:?}", address))
+    }
+
+    /// Built-in registry, used unless a registry file or collection is configured.
     pub fn default() -> ContractList {
         let mut map: ContractList = HashMap::new();
 
-        map.insert("0xc99a6a985ed2cac1ef41640596c5a5f9f4e19ef5", Contract {
-            name: "WETH",
+        map.insert("0xc99a6a985ed2cac1ef41640596c5a5f9f4e19ef5".to_string(), Contract {
+            name: "WETH".to_string(),
             decimals: 18,
-            erc: ContractType::ERC20
+            erc: ContractType::ERC20,
+            start_block: 0,
         });
 
-        map.insert("0xed4a9f48a62fb6fdcfb45bb00c9f61d1a436e58c", Contract {
-            name: "AXS",
+        map.insert("0xed4a9f48a62fb6fdcfb45bb00c9f61d1a436e58c".to_string(), Contract {
+            name: "AXS".to_string(),
             decimals: 18,
             erc: ContractType::ERC20,
+            start_block: 0,
         });
 
-        map.insert("0xa8754b9fa15fc18bb59458815510e40a12cd2014", Contract {
-            name: "SLP",
+        map.insert("0xa8754b9fa15fc18bb59458815510e40a12cd2014".to_string(), Contract {
+            name: "SLP".to_string(),
             decimals: 0,
             erc: ContractType::ERC20,
+            start_block: 0,
         });
 
-        map.insert("0x173a2d4fa585a63acd02c107d57f932be0a71bcc", Contract {
-            name: "AEC",
+        map.insert("0x173a2d4fa585a63acd02c107d57f932be0a71bcc".to_string(), Contract {
+            name: "AEC".to_string(),
             decimals: 0,
             erc: ContractType::ERC20,
+            start_block: 0,
         });
 
-        map.insert("0x0b7007c13325c48911f73a2dad5fa5dcbf808adc", Contract {
-            name: "USDC",
-            decimals: 18,
+        map.insert("0x0b7007c13325c48911f73a2dad5fa5dcbf808adc".to_string(), Contract {
+            name: "USDC".to_string(),
+            decimals: 6,
             erc: ContractType::ERC20,
+            start_block: 0,
         });
 
-        map.insert("0xe514d9deb7966c8be0ca922de8a064264ea6bcd4", Contract {
-            name: "WRON",
+        map.insert("0xe514d9deb7966c8be0ca922de8a064264ea6bcd4".to_string(), Contract {
+            name: "WRON".to_string(),
             decimals: 18,
             erc: ContractType::ERC20,
+            start_block: 0,
         });
 
-        map.insert("0x32950db2a7164ae833121501c797d79e7b79d74c", Contract {
-            name: "AXIE",
+        map.insert("0x32950db2a7164ae833121501c797d79e7b79d74c".to_string(), Contract {
+            name: "AXIE".to_string(),
             decimals: 0,
             erc: ContractType::ERC721,
+            start_block: 0,
         });
 
-        map.insert("0x8c811e3c958e190f5ec15fb376533a3398620500", Contract {
-            name: "LAND",
+        map.insert("0x8c811e3c958e190f5ec15fb376533a3398620500".to_string(), Contract {
+            name: "LAND".to_string(),
             decimals: 0,
             erc: ContractType::ERC721,
+            start_block: 0,
         });
 
-        map.insert("0xa96660f0e4a3e9bc7388925d245a6d4d79e21259", Contract {
-            name: "ITEM",
+        map.insert("0xa96660f0e4a3e9bc7388925d245a6d4d79e21259".to_string(), Contract {
+            name: "ITEM".to_string(),
             decimals: 0,
             erc: ContractType::ERC721,
+            start_block: 0,
         });
 
         map
     }
+
+    #[cfg(test)]
+    mod tests {
+        use web3::types::U256;
+
+        use super::normalize;
+
+        fn normalized(value: &str, decimals: usize) -> String {
+            normalize(U256::from_dec_str(value).unwrap(), decimals).to_string()
+        }
+
+        #[test]
+        fn normalize_zero() {
+            assert_eq!(normalized("0", 18), "0");
+            assert_eq!(normalized("0", 0), "0");
+        }
+
+        #[test]
+        fn normalize_18_decimals() {
+            assert_eq!(normalized("1500000000000000000", 18), "1.5");
+            assert_eq!(normalized("1", 18), "1E-18");
+            assert_eq!(normalized("1000000000000000000000", 18), "1000");
+        }
+
+        #[test]
+        fn normalize_without_decimals() {
+            assert_eq!(normalized("123", 0), "123");
+            assert_eq!(normalized("1000", 0), "1000");
+        }
+
+        #[test]
+        fn normalize_truncates_to_34_digits() {
+            let max = U256::MAX.to_string();
+            assert_eq!(max.len(), 78);
+            assert_eq!(normalized(&max, 18), format!("{}.{}E+59", &max[..1], &max[1..34]));
+            assert_eq!(normalized(&max, 0), format!("{}.{}E+77", &max[..1], &max[1..34]));
+        }
+    }
 }
 
 pub mod events {
@@ -166,4 +332,72 @@ pub mod events {
             anonymous: false,
         }
     }
+
+    pub fn erc_1155_transfer_single() -> Event {
+        Event {
+            name: "TransferSingle".to_string(),
+            inputs: vec![
+                EventParam {
+                    name: "_operator".to_string(),
+                    kind: ParamType::Address,
+                    indexed: true,
+                },
+                EventParam {
+                    name: "_from".to_string(),
+                    kind: ParamType::Address,
+                    indexed: true,
+                },
+                EventParam {
+                    name: "_to".to_string(),
+                    kind: ParamType::Address,
+                    indexed: true,
+                },
+                EventParam {
+                    name: "_id".to_string(),
+                    kind: ParamType::Uint(256),
+                    indexed: false,
+                },
+                EventParam {
+                    name: "_value".to_string(),
+                    kind: ParamType::Uint(256),
+                    indexed: false,
+                },
+            ],
+            anonymous: false,
+        }
+    }
+
+    pub fn erc_1155_transfer_batch() -> Event {
+        Event {
+            name: "TransferBatch".to_string(),
+            inputs: vec![
+                EventParam {
+                    name: "_operator".to_string(),
+                    kind: ParamType::Address,
+                    indexed: true,
+                },
+                EventParam {
+                    name: "_from".to_string(),
+                    kind: ParamType::Address,
+                    indexed: true,
+                },
+                EventParam {
+                    name: "_to".to_string(),
+                    kind: ParamType::Address,
+                    indexed: true,
+                },
+                EventParam {
+                    name: "_ids".to_string(),
+                    kind: ParamType::Array(Box::new(ParamType::Uint(256))),
+                    indexed: false,
+                },
+                EventParam {
+                    name: "_values".to_string(),
+                    kind: ParamType::Array(Box::new(ParamType::Uint(256))),
+                    indexed: false,
+                },
+            ],
+            anonymous: false,
+        }
+    }
 }
@@ -0,0 +1,136 @@
+use std::fmt;
+use std::process::ExitCode;
+
+use mongodb::error::ErrorKind;
+use web3::types::{Log, H256, U256, U64};
+
+/// Exit code for failures that may succeed when the importer is restarted, e.g. an unreachable
+/// node or database.
+pub const EXIT_RETRYABLE: u8 = 75;
+/// Exit code for failures that need a change in configuration (or code) before a restart helps.
+pub const EXIT_FATAL: u8 = 78;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A call to the node failed.
+    Rpc(web3::Error),
+    /// A log or block could not be decoded into a document.
+    Decode {
+        block: U64,
+        transaction: Option<H256>,
+        log_index: Option<U256>,
+        message: String,
+    },
+    /// A read or write on MongoDB failed.
+    Database(mongodb::error::Error),
+    /// A request to a game API failed.
+    Http(reqwest::Error),
+    /// The importer is misconfigured.
+    Config(String),
+    /// Another error, annotated with the block range that was being processed.
+    Blocks {
+        from: U64,
+        to: U64,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Decode error pointing at the log that could not be decoded.
+    pub fn decode(log: &Log, message: impl fmt::Display) -> Error {
+        Error::Decode {
+            block: log.block_number.unwrap_or_default(),
+            transaction: log.transaction_hash,
+            log_index: log.log_index,
+            message: message.to_string(),
+        }
+    }
+
+    /// Annotates the error with the block range being processed, unless it already names a block.
+    pub fn in_blocks(self, from: U64, to: U64) -> Error {
+        match self {
+            Error::Decode { .. } | Error::Blocks { .. } | Error::Config(_) => self,
+            source => Error::Blocks { from, to, source: Box::new(source) }
+        }
+    }
+
+    /// Whether restarting the importer may get past this error.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Rpc(_) | Error::Http(_) => true,
+            Error::Database(error) => !matches!(
+                *error.kind,
+                ErrorKind::Authentication { .. } | ErrorKind::InvalidArgument { .. }
+            ),
+            Error::Decode { .. } | Error::Config(_) => false,
+            Error::Blocks { source, .. } => source.is_retryable()
+        }
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        if self.is_retryable() {
+            ExitCode::from(EXIT_RETRYABLE)
+        } else {
+            ExitCode::from(EXIT_FATAL)
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Rpc(error) => write!(f, "RPC error: {}", error),
+            Error::Decode { block, transaction, log_index, message } => {
+                write!(f, "Decode error in block {}", block)?;
+                if let Some(transaction) = transaction {
+                    write!(f, ", transaction {:?}", transaction)?;
+                }
+                if let Some(log_index) = log_index {
+                    write!(f, ", log {}", log_index)?;
+                }
+                write!(f, ": {}", message)
+            }
+            Error::Database(error) => write!(f, "Database error: {}", error),
+            Error::Http(error) => write!(f, "HTTP error: {}", error),
+            Error::Config(message) => write!(f, "Configuration error: {}", message),
+            Error::Blocks { from, to, source } if from == to => write!(f, "Block {}: {}", from, source),
+            Error::Blocks { from, to, source } => write!(f, "Blocks {} - {}: {}", from, to, source)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<web3::Error> for Error {
+    fn from(error: web3::Error) -> Self {
+        Error::Rpc(error)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Error::Http(error)
+    }
+}
+
+impl From<mongodb::error::Error> for Error {
+    fn from(error: mongodb::error::Error) -> Self {
+        Error::Database(error)
+    }
+}
+
+/// Prints the outcome of an import and maps it to the process exit code.
+pub fn report(result: Result<()>, what: &str) -> ExitCode {
+    match result {
+        Ok(()) => {
+            println!("Finished importing {}!", what);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("An error occured during the process of importing {}! {}", what, error);
+            error.exit_code()
+        }
+    }
+}
@@ -1,24 +1,13 @@
-use std::ops::Deref;
-use futures::stream::StreamExt;
-use mongodb::bson::doc;
-use mongodb::sync::{Collection, Cursor};
-use web3::transports::WebSocket;
+use crate::config::Config;
+use crate::error::Result;
+use crate::importers::leaderboard;
+use crate::scanner;
 
-use tools::database::{MongoDb, Options};
+/// Analyses the imported battle logs.
+pub async fn run(config: &Config) -> Result<()> {
+    let _db_options = config.database_options(leaderboard::NAME);
 
-use crate::tools::types::{BattleId, ClientId, PVPBattleLog};
-
-mod tools;
-
-#[tokio::main]
-async fn main() {
-    let db_options = Options {
-        client_uri: "mongodb://127.0.0.1".to_string(),
-        database: "ronin".to_string(),
-    };
-
-    let ws = WebSocket::new("ws://localhost:8546").await.unwrap();
-    let provider = web3::Web3::new(ws);
+    let _provider = scanner::connect(&config.web3.hostnames, &config.web3.provider_type, config.transport_options()).await?;
 
 
 
@@ -44,5 +33,6 @@ async fn main() {
     //     let battle_id = log.unwrap();
     //     println!("{:?}", battle_id.first_client_fighters);
     // }
-    println!("done")
-}
\ No newline at end of file
+    println!("done");
+    Ok(())
+}
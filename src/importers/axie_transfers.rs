@@ -0,0 +1,128 @@
+use hex_literal::hex;
+use mongodb::{bson::doc, bson::DateTime, sync::Collection, IndexModel};
+use mongodb::options::IndexOptions;
+use serde::{Deserialize, Serialize};
+use web3::ethabi::{Event, RawLog};
+use web3::types::Address;
+
+use crate::config::Config;
+use crate::contracts::events;
+use crate::error::{Error, Result};
+use crate::importers;
+use crate::scanner::{self, BlockData, BlockHandler, Fetch, LogFilter};
+use crate::tools::database;
+use crate::tools::database::MongoDb;
+
+pub const NAME: &str = "axie-transfers";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transfer {
+    pub from: String,
+    pub to: String,
+    pub axie: u32,
+    pub block: u32,
+    pub created_at: DateTime,
+    pub log_id: String,
+}
+
+pub struct AxieTransferHandler {
+    collection: Collection<Transfer>,
+    axie_contract_address: Address,
+    event: Event,
+}
+
+impl AxieTransferHandler {
+    pub fn new(db: &MongoDb, config: &Config) -> Result<AxieTransferHandler> {
+        let collection = db.database.collection::<Transfer>(&config.collection(NAME));
+
+        // transfer_id was derived from (from, to, axie, block) and collided for repeated transfers of an axie within a block.
+        database::drop_unique_index(&collection, "transfer_id_1")?;
+
+        // Sparse, since transfers imported before log_id existed do not carry one.
+        let options = IndexOptions::builder().unique(true).sparse(true).build();
+        let index_model = IndexModel::builder().keys(doc! {"log_id": 1u32}).options(options).build();
+        collection.create_index(index_model, None)?;
+
+        let index_model = IndexModel::builder().keys(doc! {"axie": 1u32}).build();
+        collection.create_index(index_model, None)?;
+
+        let index_model = IndexModel::builder().keys(doc! {"from": 1u32}).build();
+        collection.create_index(index_model, None)?;
+
+        let index_model = IndexModel::builder().keys(doc! {"to": 1u32}).build();
+        collection.create_index(index_model, None)?;
+
+        let index_model = IndexModel::builder().keys(doc! {"block": 1u32}).build();
+        collection.create_index(index_model, None)?;
+
+        Ok(AxieTransferHandler {
+            collection,
+            axie_contract_address: "32950db2a7164ae833121501c797d79e7b79d74c".parse().unwrap(),
+            event: events::erc_721_transfer(),
+        })
+    }
+}
+
+impl BlockHandler for AxieTransferHandler {
+    type Document = Transfer;
+
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn label(&self) -> &str {
+        "Transfers"
+    }
+
+    fn fetch(&self) -> Fetch {
+        Fetch::Logs(vec![LogFilter {
+            addresses: vec![self.axie_contract_address],
+            topics: vec![hex!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef").into()],
+        }])
+    }
+
+    fn decode(&mut self, block: &BlockData) -> Result<Vec<Transfer>> {
+        let mut tx_pool: Vec<Transfer> = vec![];
+        for log in &block.logs {
+            let rl = RawLog {
+                topics: log.topics.clone(),
+                data: log.data.clone().0,
+            };
+
+            let data = self.event.parse_log(rl).map_err(|e| Error::decode(log, e))?;
+            let params = data.params;
+
+            let from = &params[0].value.to_string();
+            let from = f!("0x{from}");
+            let to = &params[1].value.to_string();
+            let to = f!("0x{to}");
+            let token = params[2].clone().value.into_uint().unwrap_or_default().as_u32();
+
+            let timestamp = block.timestamp.unwrap_or_default().as_u64() * 1000;
+            let timestamp = DateTime::from_millis(timestamp as i64);
+
+            tx_pool.push(Transfer {
+                from,
+                to,
+                axie: token,
+                block: block.number.as_u32(),
+                created_at: timestamp,
+                log_id: scanner::log_id(log),
+            });
+        }
+        Ok(tx_pool)
+    }
+
+    fn collection(&self) -> &Collection<Transfer> {
+        &self.collection
+    }
+
+    fn key(&self) -> Option<&str> {
+        Some("log_id")
+    }
+}
+
+/// Imports the transfers of the axie contract.
+pub async fn run(config: &Config) -> Result<()> {
+    importers::scan(config, &[NAME]).await
+}
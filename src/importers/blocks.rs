@@ -0,0 +1,86 @@
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::IndexOptions;
+use mongodb::sync::Collection;
+use mongodb::IndexModel;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::importers;
+use crate::scanner::{BlockData, BlockHandler, Fetch};
+use crate::tools::database::MongoDb;
+
+pub const NAME: &str = "blocks";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Block {
+    pub block: u32,
+    pub hash: String,
+    pub parent_hash: String,
+    pub created_at: DateTime,
+    /// Validator that sealed the block.
+    pub miner: String,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    /// Block size in bytes.
+    pub size: Option<u64>,
+    pub transaction_count: u32,
+}
+
+pub struct BlockHeaderHandler {
+    collection: Collection<Block>,
+}
+
+impl BlockHeaderHandler {
+    pub fn new(db: &MongoDb, config: &Config) -> Result<BlockHeaderHandler> {
+        let collection = db.database.collection::<Block>(&config.collection(NAME));
+        collection.create_index(IndexModel::builder().keys(doc! {"block": 1u32}).options(IndexOptions::builder().unique(true).build()).build(), None)?;
+        collection.create_index(IndexModel::builder().keys(doc! {"hash": 1u32}).build(), None)?;
+        Ok(BlockHeaderHandler { collection })
+    }
+}
+
+impl BlockHandler for BlockHeaderHandler {
+    type Document = Block;
+
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn label(&self) -> &str {
+        "Blocks"
+    }
+
+    fn fetch(&self) -> Fetch {
+        Fetch::Header
+    }
+
+    fn decode(&mut self, block: &BlockData) -> Result<Vec<Block>> {
+        let ts = block.timestamp.unwrap_or_default().as_u64() * 1000;
+        Ok(vec![Block {
+            block: block.number.as_u32(),
+            hash: web3::helpers::to_string(&block.hash).replace('"', ""),
+            parent_hash: web3::helpers::to_string(&block.parent_hash).replace('"', ""),
+            created_at: DateTime::from_millis(ts as i64),
+            miner: web3::helpers::to_string(&block.miner).replace('"', ""),
+            gas_used: block.gas_used.low_u64(),
+            gas_limit: block.gas_limit.low_u64(),
+            size: block.size.map(|size| size.low_u64()),
+            transaction_count: block.transaction_count as u32,
+        }])
+    }
+
+    fn collection(&self) -> &Collection<Block> {
+        &self.collection
+    }
+
+    /// A block re-imported after a chain reorganisation replaces the orphaned one.
+    fn key(&self) -> Option<&str> {
+        Some("block")
+    }
+}
+
+/// Imports the header of every block.
+pub async fn run(config: &Config) -> Result<()> {
+    importers::scan(config, &[NAME]).await
+}
@@ -0,0 +1,267 @@
+use hex_literal::hex;
+use mongodb::bson::{Bson, DateTime, doc};
+use mongodb::IndexModel;
+use mongodb::options::{AggregateOptions, IndexOptions};
+use mongodb::sync::Collection;
+use web3::ethabi::{Event, RawLog, Token};
+use web3::types::{Address, U64};
+
+use crate::config::Config;
+use crate::contracts::contracts::{ContractList, ContractType};
+use crate::contracts::database::Transfer;
+use crate::contracts;
+use crate::error::{Error, Result};
+use crate::importers;
+use crate::scanner::{self, BlockData, BlockHandler, Fetch, Importer, LogFilter, Scanner};
+use crate::tools::database::MongoDb;
+
+pub const NAME: &str = "erc-transfer";
+
+pub struct ErcTransferHandler {
+    /// Service name, backfills of single contracts keep their own checkpoint.
+    name: String,
+    collection: Collection<Transfer>,
+    contracts: ContractList,
+    erc_20_transfer: Event,
+    erc_721_transfer: Event,
+    erc_1155_transfer_single: Event,
+    erc_1155_transfer_batch: Event,
+}
+
+impl ErcTransferHandler {
+    pub fn new(db: &MongoDb, config: &Config) -> Result<ErcTransferHandler> {
+        let collection = db.database.collection::<Transfer>(&config.collection(NAME));
+
+        collection.create_index(IndexModel::builder().keys(doc! {"log_id": 1u32}).options(IndexOptions::builder().unique(true).build()).build(), None)?;
+        collection.create_index(IndexModel::builder().keys(doc! {"from": 1u32}).build(), None)?;
+        collection.create_index(IndexModel::builder().keys(doc! {"to": 1u32}).build(), None)?;
+        collection.create_index(IndexModel::builder().keys(doc! {"token": 1u32}).build(), None)?;
+        collection.create_index(IndexModel::builder().keys(doc! {"value_or_token_id": 1u32}).build(), None)?;
+        collection.create_index(IndexModel::builder().keys(doc! {"block": 1u32}).build(), None)?;
+        collection.create_index(IndexModel::builder().keys(doc! {"erc": 1u32}).build(), None)?;
+        collection.create_index(IndexModel::builder().keys(doc! {"transaction_id": 1u32}).build(), None)?;
+
+        Ok(ErcTransferHandler {
+            name: NAME.to_string(),
+            collection,
+            contracts: contracts::contracts::load(&config.contracts, db)?,
+            erc_20_transfer: contracts::events::erc_20_transfer(),
+            erc_721_transfer: contracts::events::erc_721_transfer(),
+            erc_1155_transfer_single: contracts::events::erc_1155_transfer_single(),
+            erc_1155_transfer_batch: contracts::events::erc_1155_transfer_batch(),
+        })
+    }
+}
+
+impl BlockHandler for ErcTransferHandler {
+    type Document = Transfer;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn label(&self) -> &str {
+        "Transfers"
+    }
+
+    fn fetch(&self) -> Fetch {
+        Fetch::Logs(vec![LogFilter {
+            addresses: contracts::contracts::addresses(&self.contracts),
+            topics: vec![
+                hex!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef").into(),
+                self.erc_1155_transfer_single.signature(),
+                self.erc_1155_transfer_batch.signature(),
+            ],
+        }])
+    }
+
+    fn decode(&mut self, block: &BlockData) -> Result<Vec<Transfer>> {
+        let ts = block.timestamp.unwrap_or_default().as_u64() * 1000;
+        let mut tx_pool: Vec<Transfer> = vec![];
+
+        for log in &block.logs {
+            let contract = match contracts::contracts::find(&self.contracts, &log.address) {
+                Some(contract) if block.number.as_u32() >= contract.start_block => contract,
+                _ => continue
+            };
+
+            let topic = log.topics.first().copied().unwrap_or_default();
+            let event = match contract.erc {
+                ContractType::ERC20 => &self.erc_20_transfer,
+                ContractType::ERC721 => &self.erc_721_transfer,
+                ContractType::ERC1155 if topic == self.erc_1155_transfer_batch.signature() => &self.erc_1155_transfer_batch,
+                ContractType::ERC1155 => &self.erc_1155_transfer_single,
+                ContractType::Unknown => continue
+            };
+            if topic != event.signature() {
+                continue;
+            }
+
+            let raw_log = RawLog {
+                topics: log.topics.clone(),
+                data: log.data.0.clone()
+            };
+            let data = event.parse_log(raw_log).map_err(|e| Error::decode(log, e))?.params;
+
+            // (from, to, token id or value, quantity, log id) of every transferred token. Only a
+            // batch transfers several, which get one document and log id each.
+            let log_id = scanner::log_id(log);
+            let transfers: Vec<(&Token, &Token, Token, Option<Token>, String)> = match contract.erc {
+                ContractType::ERC1155 if topic == self.erc_1155_transfer_batch.signature() => {
+                    let ids = data[3].value.clone().into_array().unwrap_or_default();
+                    let values = data[4].value.clone().into_array().unwrap_or_default();
+                    ids.into_iter().zip(values).enumerate()
+                        .map(|(i, (id, value))| (&data[1].value, &data[2].value, id, Some(value), f!("{log_id}-{i}")))
+                        .collect()
+                }
+                ContractType::ERC1155 => vec![(&data[1].value, &data[2].value, data[3].value.clone(), Some(data[4].value.clone()), log_id)],
+                _ => vec![(&data[0].value, &data[1].value, data[2].value.clone(), None, log_id)]
+            };
+
+            let transaction_hash = log.transaction_hash.unwrap_or_default();
+            let log_index = log.log_index.unwrap_or_default();
+
+            for (from, to, value, quantity, log_id) in transfers {
+                tx_pool.push(Transfer {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    token: web3::helpers::to_string(&log.address).replace('"', ""),
+                    value_or_token_id: value.to_string(),
+                    amount: value.into_uint().and_then(|value| contract.amount(value)),
+                    symbol: contract.name.to_string(),
+                    created_at: DateTime::from_millis(ts as i64),
+                    block: block.number.as_u64(),
+                    transaction_id: web3::helpers::to_string(&transaction_hash).replace('"', ""),
+                    erc: contract.erc,
+                    log_index: web3::helpers::to_string(&log_index).replace('"', ""),
+                    quantity: quantity.and_then(|quantity| quantity.into_uint()).map(|quantity| quantity.to_string()),
+                    log_id
+                });
+            }
+        }
+
+        Ok(tx_pool)
+    }
+
+    fn collection(&self) -> &Collection<Transfer> {
+        &self.collection
+    }
+
+    fn key(&self) -> Option<&str> {
+        Some("log_id")
+    }
+
+    /// Only removes transfers of the handler's own contracts, a backfill shares the collection with
+    /// the main import and must not touch the transfers of the other contracts.
+    fn rollback(&self, block: U64) -> Result<()> {
+        let tokens: Vec<&String> = self.contracts.keys().collect();
+        self.collection.delete_many(doc! {"block": {"$gt": block.as_u64() as i64}, "token": {"$in": tokens}}, None)?;
+        Ok(())
+    }
+}
+
+/// Imports the transfers of all known ERC20, ERC721 and ERC1155 contracts.
+pub async fn run(config: &Config) -> Result<()> {
+    importers::scan(config, &[NAME]).await
+}
+
+/// Imports the transfers of the registered contracts `addresses` from their start block up to the
+/// checkpoint of the erc-transfer importer, e.g. after adding them to the registry. The backfill
+/// keeps its own checkpoint and resumes from it when restarted.
+pub async fn backfill(config: &Config, addresses: &[String]) -> Result<()> {
+    let db = MongoDb::new(config.database_options(NAME)).await?;
+    let mut handler = ErcTransferHandler::new(&db, config)?;
+
+    let mut selected = ContractList::new();
+    for address in addresses {
+        let key = address.parse::<Address>().map(|address| format!("{:?}", address)).unwrap_or_default();
+        let contract = handler.contracts.remove(&key)
+            .ok_or_else(|| Error::Config(format!("{} is not a registered contract", address)))?;
+        selected.insert(key, contract);
+    }
+    let mut keys: Vec<&String> = selected.keys().collect();
+    keys.sort();
+    handler.name = format!("{}:{}", NAME, keys.iter().map(|key| key.as_str()).collect::<Vec<_>>().join(","));
+
+    let start_block = match db.get_checkpoint(&handler.name)? {
+        Some(checkpoint) => checkpoint as u32 + 1,
+        None => selected.values().map(|contract| contract.start_block).min().unwrap_or_default()
+    };
+    let end_block = match db.get_checkpoint(NAME)? {
+        Some(checkpoint) => checkpoint as u32,
+        None => return Err(Error::Config(format!("{} has no checkpoint yet, run it instead of a backfill", NAME)))
+    };
+    if start_block > end_block {
+        println!("Backfill is already complete up to block {}", end_block);
+        return Ok(());
+    }
+    handler.contracts = selected;
+
+    let mut options = config.scanner_options(NAME);
+    options.start_block = std::cmp::max(start_block, 1);
+    options.end_block = end_block;
+    options.follow = false;
+
+    let web3 = scanner::connect(&config.web3.hostnames, &config.web3.provider_type, config.transport_options()).await?;
+    let headers = importers::header_cache(config, &web3).await?;
+    let importer = Importer::new(handler, db.clone(), &options);
+    Scanner::new(web3, headers, vec![importer], options).run().await
+}
+
+/// Number of blocks whose timestamps are resolved and written at a time by [`repair_timestamps`].
+const REPAIR_BATCH: usize = 100;
+
+/// Rewrites `created_at` of already imported transfers to the timestamp of their block. Older
+/// versions stored the time of the import instead. Only touches blocks between the configured
+/// start and end block.
+pub async fn repair_timestamps(config: &Config) -> Result<()> {
+    let db = MongoDb::new(config.database_options(NAME)).await?;
+    let collection = db.database.collection::<Transfer>(&config.collection(NAME));
+    let options = config.scanner_options(NAME);
+    let web3 = scanner::connect(&config.web3.hostnames, &config.web3.provider_type, config.transport_options()).await?;
+    let headers = importers::header_cache(config, &web3).await?;
+
+    let mut range = doc! {"$gte": options.start_block as i64};
+    if options.end_block != 0 {
+        range.insert("$lte", options.end_block as i64);
+    }
+    let pipeline = vec![
+        doc! {"$match": {"block": range}},
+        doc! {"$group": {"_id": "$block"}},
+        doc! {"$sort": {"_id": 1}},
+    ];
+    // Distinct blocks are streamed from the cursor, a full import has millions of them.
+    let mut blocks = collection.aggregate(pipeline, AggregateOptions::builder().allow_disk_use(true).build())?
+        .filter_map(|result| match result {
+            Ok(group) => match group.get("_id") {
+                Some(Bson::Int32(block)) => Some(Ok(*block as u64)),
+                Some(Bson::Int64(block)) => Some(Ok(*block as u64)),
+                _ => None
+            },
+            Err(error) => Some(Err(error))
+        });
+
+    let mut repaired = 0;
+    loop {
+        let batch = blocks.by_ref().take(REPAIR_BATCH).collect::<mongodb::error::Result<Vec<u64>>>()?;
+        if batch.is_empty() {
+            break;
+        }
+        let numbers: Vec<U64> = batch.iter().map(|block| U64::from(*block)).collect();
+        for header in headers.headers(&numbers).await? {
+            let block = header.number.as_u64();
+            let created_at = DateTime::from_millis(header.timestamp.as_u64() as i64 * 1000);
+            let result = collection.update_many(
+                doc! {"block": block as i64, "created_at": {"$ne": created_at}},
+                doc! {"$set": {"created_at": created_at}},
+                None,
+            )?;
+            repaired += result.modified_count;
+        }
+
+        println!("Blocks: {} - {}\tRepaired: {}", batch[0], batch[batch.len() - 1], repaired);
+    }
+
+    println!("Repaired the timestamps of {} transfers", repaired);
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use crate::tools::types::*;
+use mongodb::{bson::doc, sync::Collection, IndexModel};
+use mongodb::options::IndexOptions;
+use crate::config::Config;
+use crate::error::Result;
+use crate::tools::origin::leaderboard::{self, LeaderboardItem};
+
+use crate::tools::database::*;
+
+pub const NAME: &str = "battle-logs";
+
+/// Imports the battle logs of the players at the top of the leaderboard.
+pub async fn run(config: &Config) -> Result<()> {
+    let leaderboard: Vec<LeaderboardItem> = leaderboard::get_leaderboard_page(1).await?;
+
+    let db = MongoDb::new(config.database_options(NAME)).await?;
+
+    let collection: Collection<PVPBattleLog> = db.database.collection::<PVPBattleLog>(&config.collection(NAME));
+
+    let options = IndexOptions::builder().unique(true).build();
+    let index_model = IndexModel::builder().keys(doc! {"battle_uuid": 1u32}).options(options).build();
+    collection.create_index(index_model, None)?;
+
+    let mut items_to_insert: Vec<PVPBattleLog> = vec![];
+
+    for player in leaderboard {
+        println!("Checking Leaderboard for:");
+        println!("Rank: {}", player.top_rank);
+        println!("Name: {}", player.name);
+        println!("ID: {}", player.user_id);
+        println!("Stars: {}", player.vstar);
+
+        let url: String = f!("https://tracking.skymavis.com/origin/battle-history?type=pvp&client_id={player.user_id}");
+
+        let client = reqwest::Client::new();
+        let result: std::result::Result<BattleLogResult, reqwest::Error> = match client.get(url).send().await {
+            Ok(response) => response.json().await,
+            Err(error) => Err(error)
+        };
+
+        let mut battles = match result {
+            Ok(result) => result.battles,
+            Err(error) => {
+                println!("Failed to fetch battles: {}\n====================", error);
+                continue;
+            }
+        };
+
+        items_to_insert.append(&mut battles);
+
+        println!("Battles: {}\n====================", battles.len());
+
+    }
+
+    let summary = insert_unordered(&collection, items_to_insert)?;
+
+    println!("Added {} battle logs to the database! ({} already known)", summary.inserted, summary.duplicates);
+
+    // db.update_health(String::from("battlelog-analyser"));
+
+    Ok(())
+}
@@ -0,0 +1,134 @@
+pub mod analyse;
+pub mod axie_transfers;
+pub mod blocks;
+pub mod erc_transfers;
+pub mod leaderboard;
+pub mod sales;
+pub mod stats;
+pub mod transactions;
+pub mod verify;
+
+use std::collections::HashMap;
+
+use mongodb::bson::doc;
+use web3::Web3;
+
+use crate::cache::HeaderCache;
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::scanner::{self, Importer, Scanner, Transport};
+use crate::tools::database::MongoDb;
+
+/// Service names of all importers. They key the config sections, checkpoints and health entries.
+pub const ALL: [&str; 7] = [
+    blocks::NAME,
+    transactions::NAME,
+    axie_transfers::NAME,
+    sales::NAME,
+    erc_transfers::NAME,
+    stats::NAME,
+    leaderboard::NAME,
+];
+
+/// Importers that can share a scan, i.e. all importers except the battle logs.
+pub const BLOCK_IMPORTERS: [&str; 6] = [
+    blocks::NAME,
+    transactions::NAME,
+    axie_transfers::NAME,
+    sales::NAME,
+    erc_transfers::NAME,
+    stats::NAME,
+];
+
+/// Subcommands of the [`BLOCK_IMPORTERS`], in the same order.
+pub const BLOCK_SUBCOMMANDS: [&str; 6] = ["blocks", "transactions", "axie-transfers", "sales", "erc-transfers", "stats"];
+
+/// Service name of the block importer started by the subcommand `command`. Service names map to
+/// themselves.
+pub fn service_name(command: &str) -> Option<&'static str> {
+    BLOCK_SUBCOMMANDS.iter().zip(BLOCK_IMPORTERS)
+        .find(|(subcommand, name)| **subcommand == command || *name == command)
+        .map(|(_, name)| name)
+}
+
+/// Connects to the database of importer `name` and registers its handler.
+async fn importer(config: &Config, name: &str) -> Result<Importer> {
+    let db = MongoDb::new(config.database_options(name)).await?;
+    let options = config.scanner_options(name);
+    Ok(match name {
+        blocks::NAME => Importer::new(blocks::BlockHeaderHandler::new(&db, config)?, db, &options),
+        transactions::NAME => Importer::new(transactions::TransactionHandler::new(&db, config)?, db, &options),
+        axie_transfers::NAME => Importer::new(axie_transfers::AxieTransferHandler::new(&db, config)?, db, &options),
+        sales::NAME => Importer::new(sales::SaleHandler::new(&db, config)?, db, &options),
+        erc_transfers::NAME => Importer::new(erc_transfers::ErcTransferHandler::new(&db, config)?, db, &options),
+        stats::NAME => Importer::new(stats::BlockStatsHandler::new(&db, config)?, db, &options),
+        _ => return Err(Error::Config(format!("{} is not a block importer, expected one of {}", name, BLOCK_IMPORTERS.join(", "))))
+    })
+}
+
+/// Runs the importers `names` in a single pass over the chain, so every block is only fetched
+/// once. Each importer resumes from its own checkpoint. The scan keeps the highest number of
+/// confirmations of all importers behind the chain head. Fails if two importers would write to the
+/// same collection, as they would overwrite and roll back each other's documents.
+pub async fn scan(config: &Config, names: &[&str]) -> Result<()> {
+    let mut namespaces: HashMap<String, &str> = HashMap::new();
+    for name in names {
+        let namespace = format!("{}.{}", config.database_options(name).database, config.collection(name));
+        if let Some(other) = namespaces.insert(namespace.clone(), name) {
+            return Err(Error::Config(format!("{} and {} both write to {}, configure a collection for one of them", other, name, namespace)));
+        }
+    }
+
+    let mut importers = vec![];
+    for name in names {
+        importers.push(importer(config, name).await?);
+    }
+
+    let mut options = config.scanner_options(names.first().copied().unwrap_or_default());
+    options.confirmations = names.iter().map(|name| config.scanner_options(name).confirmations).max().unwrap_or_default();
+
+    let web3 = scanner::connect(&config.web3.hostnames, &config.web3.provider_type, config.transport_options()).await?;
+    let headers = header_cache(config, &web3).await?;
+    let mut scanner = Scanner::new(web3, headers, importers, options);
+    scanner.run().await?;
+
+    for importer in scanner.importers() {
+        importer.db().update_health(importer.name().into());
+    }
+
+    Ok(())
+}
+
+/// Block header cache configured in `[scanner]`, backed by the collection of the blocks importer
+/// if `headers_from_db` is set.
+pub async fn header_cache(config: &Config, web3: &Web3<Transport>) -> Result<HeaderCache> {
+    let blocks = if config.scanner.headers_from_db {
+        let db = MongoDb::new(config.database_options(blocks::NAME)).await?;
+        Some(db.database.collection::<blocks::Block>(&config.collection(blocks::NAME)))
+    } else {
+        None
+    };
+    Ok(HeaderCache::new(web3.clone(), config.scanner.header_cache, blocks))
+}
+
+/// Prints the collection, number of documents and checkpoint of every importer.
+pub async fn list(config: &Config) -> Result<()> {
+    println!("{:<16}{:<32}{:>12}{:>12}  Updated", "Importer", "Collection", "Documents", "Checkpoint");
+
+    for name in ALL {
+        let options = config.database_options(name);
+        let namespace = format!("{}.{}", options.database, config.collection(name));
+        let db = MongoDb::new(options).await?;
+
+        let documents = db.database.collection::<mongodb::bson::Document>(&config.collection(name)).estimated_document_count(None)?;
+        let (checkpoint, updated) = match db.checkpoint_collection().find_one(doc! {"name": name}, None)? {
+            Some(checkpoint) => (checkpoint.block.to_string(), checkpoint.updated_at.to_string()),
+            None => ("-".to_string(), "-".to_string())
+        };
+
+        println!("{:<16}{:<32}{:>12}{:>12}  {}", name, namespace, documents, checkpoint, updated);
+    }
+
+    Ok(())
+}
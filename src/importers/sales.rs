@@ -0,0 +1,341 @@
+use hex_literal::hex;
+use mongodb::{bson::DateTime, bson::Decimal128, bson::doc, IndexModel, sync::Collection};
+use mongodb::options::IndexOptions;
+use serde::{Deserialize, Serialize};
+use web3::ethabi::{Event, EventParam, ParamType, RawLog};
+use web3::types::{Address, Log, H256, U256};
+
+use crate::config::Config;
+use crate::contracts::contracts::{self, ContractList};
+use crate::contracts::events;
+use crate::error::{Error, Result};
+use crate::importers;
+use crate::scanner::{self, BlockData, BlockHandler, Fetch, LogFilter};
+use crate::tools::database::{self, MongoDb};
+
+pub const NAME: &str = "axie-sales";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sale {
+    pub seller: String,
+    pub buyer: String,
+    /// `None` if none of the axie transfers of the transaction could be matched to the sale.
+    pub axie: Option<usize>,
+    pub block: u32,
+    /// Price in the smallest unit of `token`, e.g. wei.
+    pub price: String,
+    /// Price in whole tokens, if `token` is a registered ERC20 contract. Only set on the first
+    /// document of a bundle, so summing it counts every sale once.
+    pub price_amount: Option<Decimal128>,
+    pub token: String,
+    /// Symbol of `token`, if it is a registered contract.
+    pub symbol: Option<String>,
+    pub transaction_id: String,
+    pub created_at: DateTime,
+    /// Id of the axie transfer log, a sale of several axies has one document per axie. Id of the
+    /// sale log if no transfer was matched.
+    pub log_id: String,
+}
+
+fn auction_successful_event() -> Event {
+    Event {
+        name: "AuctionSuccessful".to_string(),
+        inputs: vec![
+            EventParam {
+                name: "_seller".to_string(),
+                kind: ParamType::Address,
+                indexed: false,
+            },
+            EventParam {
+                name: "_buyer".to_string(),
+                kind: ParamType::Address,
+                indexed: false,
+            },
+            EventParam {
+                name: "_listingIndex".to_string(),
+                kind: ParamType::Uint(256),
+                indexed: false,
+            },
+            EventParam {
+                name: "_token".to_string(),
+                kind: ParamType::Address,
+                indexed: false,
+            },
+            EventParam {
+                name: "_totalPrice".to_string(),
+                kind: ParamType::Uint(256),
+                indexed: false,
+            },
+        ],
+        anonymous: false,
+    }
+}
+
+/// A decoded `AuctionSuccessful` log.
+struct SaleLog<'a> {
+    log: &'a Log,
+    seller: Address,
+    buyer: Address,
+    token: Address,
+    total_price: U256,
+}
+
+/// A decoded axie `Transfer` log.
+struct TransferLog<'a> {
+    log: &'a Log,
+    from: Address,
+    to: Address,
+    axie: usize,
+}
+
+/// Assigns the axie transfers of one transaction to its sales and returns the transfer indices of
+/// every sale. A transfer goes to a sale whose buyer receives it, preferring one whose seller sends
+/// it, so bulk purchases and bundles (one sale, several axies) are both matched. Every sale gets
+/// a transfer first, the remaining ones are added to the sales as bundles.
+fn pair(sales: &[SaleLog], transfers: &[TransferLog]) -> Vec<Vec<usize>> {
+    let matches = |sale: &SaleLog, transfer: &TransferLog, strict: bool| {
+        transfer.to == sale.buyer && (!strict || transfer.from == sale.seller)
+    };
+    let mut pairs = vec![vec![]; sales.len()];
+    let mut used = vec![false; transfers.len()];
+
+    for (i, sale) in sales.iter().enumerate() {
+        for strict in [true, false] {
+            if let Some(j) = (0..transfers.len()).find(|&j| !used[j] && matches(sale, &transfers[j], strict)) {
+                used[j] = true;
+                pairs[i].push(j);
+                break;
+            }
+        }
+    }
+
+    for (j, transfer) in transfers.iter().enumerate().filter(|(j, _)| !used[*j]) {
+        for strict in [true, false] {
+            if let Some(i) = sales.iter().position(|sale| matches(sale, transfer, strict)) {
+                pairs[i].push(j);
+                break;
+            }
+        }
+    }
+
+    pairs
+}
+
+pub struct SaleHandler {
+    collection: Collection<Sale>,
+    contract_address: Address,
+    axie_contract_address: Address,
+    auction_successful_event: Event,
+    axie_transfer_event: Event,
+    /// Payment tokens, for the normalized price.
+    contracts: ContractList,
+}
+
+impl SaleHandler {
+    pub fn new(db: &MongoDb, config: &Config) -> Result<SaleHandler> {
+        let collection = db.database.collection::<Sale>(&config.collection(NAME));
+
+        // A transaction can contain several sales, so transaction_id is no longer unique.
+        database::drop_unique_index(&collection, "transaction_id_1")?;
+
+        let index_model = IndexModel::builder().keys(doc! {"transaction_id": 1u32}).build();
+        collection.create_index(index_model, None)?;
+
+        // Sparse, since sales imported before log_id existed do not carry one.
+        let options = IndexOptions::builder().unique(true).sparse(true).build();
+        let index_model = IndexModel::builder().keys(doc! {"log_id": 1u32}).options(options).build();
+        collection.create_index(index_model, None)?;
+
+        let index_model = IndexModel::builder().keys(doc! {"buyer": 1u32}).build();
+        collection.create_index(index_model, None)?;
+
+        let index_model = IndexModel::builder().keys(doc! {"seller": 1u32}).build();
+        collection.create_index(index_model, None)?;
+
+        let index_model = IndexModel::builder().keys(doc! {"axie": 1u32}).build();
+        collection.create_index(index_model, None)?;
+
+        let index_model = IndexModel::builder().keys(doc! {"block": 1u32}).build();
+        collection.create_index(index_model, None)?;
+
+        let index_model = IndexModel::builder().keys(doc! {"created_at": 1u32}).build();
+        collection.create_index(index_model, None)?;
+
+        Ok(SaleHandler {
+            collection,
+            contract_address: "213073989821f738A7BA3520C3D31a1F9aD31bBd".parse().unwrap(),
+            axie_contract_address: "32950db2a7164ae833121501c797d79e7b79d74c".parse().unwrap(),
+            auction_successful_event: auction_successful_event(),
+            axie_transfer_event: events::erc_721_transfer(),
+            contracts: contracts::load(&config.contracts, db)?,
+        })
+    }
+}
+
+impl BlockHandler for SaleHandler {
+    type Document = Sale;
+
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn label(&self) -> &str {
+        "Sales"
+    }
+
+    /// Sales are matched with the axie transfers of the same transaction, so both are fetched.
+    fn fetch(&self) -> Fetch {
+        Fetch::Logs(vec![LogFilter {
+            addresses: vec![self.contract_address, self.axie_contract_address],
+            topics: vec![
+                hex!("0c0258cd7f0d9474f62106c6981c027ea54bee0b323ea1991f4caa7e288a5725").into(),
+                hex!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef").into(),
+            ],
+        }])
+    }
+
+    fn decode(&mut self, block: &BlockData) -> Result<Vec<Sale>> {
+        let mut tx_pool: Vec<Sale> = vec![];
+
+        let timestamp = block.timestamp.unwrap_or_default().as_u64() * 1000;
+        let timestamp = DateTime::from_millis(timestamp as i64);
+
+        let mut transactions: Vec<H256> = block.logs.iter().filter_map(|log| log.transaction_hash).collect();
+        transactions.dedup();
+
+        for transaction in transactions {
+            let logs = block.logs.iter().filter(|log| log.transaction_hash == Some(transaction));
+
+            let mut sales = vec![];
+            let mut transfers = vec![];
+            for log in logs {
+                let raw = RawLog {
+                    topics: log.topics.clone(),
+                    data: log.data.clone().0,
+                };
+                if log.address == self.contract_address {
+                    let params = self.auction_successful_event.parse_log(raw).map_err(|e| Error::decode(log, e))?.params;
+                    sales.push(SaleLog {
+                        log,
+                        seller: params[0].value.clone().into_address().unwrap_or_default(),
+                        buyer: params[1].value.clone().into_address().unwrap_or_default(),
+                        token: params[3].value.clone().into_address().unwrap_or_default(),
+                        total_price: params[4].value.clone().into_uint().unwrap_or_default(),
+                    });
+                } else if log.address == self.axie_contract_address {
+                    let params = self.axie_transfer_event.parse_log(raw).map_err(|e| Error::decode(log, e))?.params;
+                    transfers.push(TransferLog {
+                        log,
+                        from: params[0].value.clone().into_address().unwrap_or_default(),
+                        to: params[1].value.clone().into_address().unwrap_or_default(),
+                        axie: params[2].value.clone().into_uint().unwrap_or_default().as_usize(),
+                    });
+                }
+            }
+
+            for (sale, matched) in sales.iter().zip(pair(&sales, &transfers)) {
+                let contract = contracts::find(&self.contracts, &sale.token);
+                let document = |axie: Option<usize>, log: &Log, first: bool| Sale {
+                    seller: f!("{:?}", sale.seller),
+                    buyer: f!("{:?}", sale.buyer),
+                    axie,
+                    block: block.number.as_u32(),
+                    price: sale.total_price.to_string(),
+                    price_amount: contract.filter(|_| first).and_then(|contract| contract.amount(sale.total_price)),
+                    token: f!("{:?}", sale.token),
+                    symbol: contract.map(|contract| contract.name.to_string()),
+                    transaction_id: web3::helpers::to_string(&transaction).replace('"', ""),
+                    created_at: timestamp,
+                    log_id: scanner::log_id(log),
+                };
+
+                if matched.is_empty() {
+                    println!("No axie transfer matches the sale in transaction {:?}, storing it without an axie", transaction);
+                    tx_pool.push(document(None, sale.log, true));
+                }
+                for (n, j) in matched.into_iter().enumerate() {
+                    tx_pool.push(document(Some(transfers[j].axie), transfers[j].log, n == 0));
+                }
+            }
+        }
+
+        Ok(tx_pool)
+    }
+
+    fn collection(&self) -> &Collection<Sale> {
+        &self.collection
+    }
+
+    fn key(&self) -> Option<&str> {
+        Some("log_id")
+    }
+}
+
+/// Imports the axie sales of the marketplace.
+pub async fn run(config: &Config) -> Result<()> {
+    importers::scan(config, &[NAME]).await
+}
+
+#[cfg(test)]
+mod tests {
+    use web3::types::{Address, Log, U256};
+
+    use super::{pair, SaleLog, TransferLog};
+
+    fn log() -> Log {
+        serde_json::from_str(r#"{"address": "0x0000000000000000000000000000000000000000", "topics": [], "data": "0x"}"#).unwrap()
+    }
+
+    fn address(n: u64) -> Address {
+        Address::from_low_u64_be(n)
+    }
+
+    fn sale(log: &Log, seller: u64, buyer: u64) -> SaleLog<'_> {
+        SaleLog { log, seller: address(seller), buyer: address(buyer), token: Address::zero(), total_price: U256::one() }
+    }
+
+    fn transfer(log: &Log, from: u64, to: u64, axie: usize) -> TransferLog<'_> {
+        TransferLog { log, from: address(from), to: address(to), axie }
+    }
+
+    #[test]
+    fn bulk_purchase_pairs_every_sale_with_its_transfer() {
+        let log = log();
+        let sales = [sale(&log, 1, 9), sale(&log, 2, 9), sale(&log, 3, 9)];
+        let transfers = [transfer(&log, 3, 9, 30), transfer(&log, 1, 9, 10), transfer(&log, 2, 9, 20)];
+        assert_eq!(pair(&sales, &transfers), vec![vec![1], vec![2], vec![0]]);
+    }
+
+    #[test]
+    fn bundle_gets_all_transfers() {
+        let log = log();
+        let sales = [sale(&log, 1, 9)];
+        let transfers = [transfer(&log, 1, 9, 10), transfer(&log, 1, 9, 11), transfer(&log, 1, 9, 12)];
+        assert_eq!(pair(&sales, &transfers), vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn escrowed_transfer_matches_the_buyer() {
+        let log = log();
+        let sales = [sale(&log, 1, 9)];
+        let transfers = [transfer(&log, 5, 9, 10)];
+        assert_eq!(pair(&sales, &transfers), vec![vec![0]]);
+    }
+
+    #[test]
+    fn sale_without_matching_transfer_stays_empty() {
+        let log = log();
+        let sales = [sale(&log, 1, 9), sale(&log, 2, 8)];
+        let transfers = [transfer(&log, 1, 9, 10), transfer(&log, 2, 7, 20)];
+        assert_eq!(pair(&sales, &transfers), vec![vec![0], vec![]]);
+    }
+
+    #[test]
+    fn resale_within_one_transaction() {
+        // A sells to B, B sells back to A, A sells to B again.
+        let log = log();
+        let sales = [sale(&log, 1, 2), sale(&log, 2, 1), sale(&log, 1, 2)];
+        let transfers = [transfer(&log, 1, 2, 10), transfer(&log, 2, 1, 10), transfer(&log, 1, 2, 10)];
+        assert_eq!(pair(&sales, &transfers), vec![vec![0], vec![1], vec![2]]);
+    }
+}
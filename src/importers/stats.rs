@@ -0,0 +1,65 @@
+use mongodb::sync::Collection;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::importers;
+use crate::scanner::{BlockData, BlockHandler, Fetch};
+use crate::tools::database::{self, MongoDb};
+
+pub const NAME: &str = "block-stats";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlockStats {
+    pub block: isize,
+    pub tx_num: isize,
+}
+
+pub struct BlockStatsHandler {
+    collection: Collection<BlockStats>,
+}
+
+impl BlockStatsHandler {
+    pub fn new(db: &MongoDb, config: &Config) -> Result<BlockStatsHandler> {
+        let collection = db.database.collection::<BlockStats>(&config.collection(NAME));
+        database::create_unique_index(&collection, "block")?;
+        Ok(BlockStatsHandler { collection })
+    }
+}
+
+impl BlockHandler for BlockStatsHandler {
+    type Document = BlockStats;
+
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn label(&self) -> &str {
+        "Transactions"
+    }
+
+    fn fetch(&self) -> Fetch {
+        Fetch::Header
+    }
+
+    fn decode(&mut self, block: &BlockData) -> Result<Vec<BlockStats>> {
+        Ok(vec![BlockStats {
+            block: block.number.as_u64() as isize,
+            tx_num: block.transaction_count as isize,
+        }])
+    }
+
+    fn collection(&self) -> &Collection<BlockStats> {
+        &self.collection
+    }
+
+    /// Statistics re-imported after a chain reorganisation replace the orphaned ones.
+    fn key(&self) -> Option<&str> {
+        Some("block")
+    }
+}
+
+/// Imports the number of transactions per block.
+pub async fn run(config: &Config) -> Result<()> {
+    importers::scan(config, &[NAME]).await
+}
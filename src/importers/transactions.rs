@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use mongodb::{bson::doc, bson::DateTime, sync::Collection, IndexModel};
+use mongodb::options::IndexOptions;
+use serde::{Deserialize, Serialize};
+use web3::signing::keccak256;
+use web3::types::{TransactionReceipt, H256, U64};
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::importers;
+use crate::scanner::{BlockData, BlockHandler, Fetch};
+use crate::tools::database::{self, MongoDb, WriteSummary};
+
+pub const NAME: &str = "transactions";
+
+/// Collection of the contracts deployed by imported transactions, next to the transactions.
+pub const DEPLOYMENTS_COLLECTION: &str = "contracts_deployed";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Transaction {
+    pub from: String,
+    /// `None` for contract creations.
+    pub to: Option<String>,
+    pub hash: String,
+    pub block: u32,
+    pub created_at: DateTime,
+    pub transaction_index: Option<u32>,
+    pub nonce: u64,
+    /// Transferred value in wei, as a decimal string.
+    pub value: String,
+    /// Gas limit of the transaction.
+    pub gas: u64,
+    /// Gas price in wei, as a decimal string.
+    pub gas_price: String,
+    /// Call data, hex encoded.
+    pub input: String,
+    /// 1 for success, 0 for failure. Receipt fields are only set if receipts are enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_used: Option<u64>,
+    /// Address of the contract created by the transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_count: Option<u32>,
+    /// Contract created by the transaction, written to [`DEPLOYMENTS_COLLECTION`].
+    #[serde(skip)]
+    pub deployment: Option<ContractDeployment>,
+}
+
+/// A contract deployed directly by a transaction. Contracts created by other contracts (e.g.
+/// factories) do not show up in the receipt and are not recorded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractDeployment {
+    pub address: String,
+    pub deployer: String,
+    pub block: u32,
+    pub transaction_id: String,
+    pub created_at: DateTime,
+    /// keccak256 of the creation bytecode, equal for deployments of the same contract with the same constructor arguments.
+    pub bytecode_hash: String,
+}
+
+pub struct TransactionHandler {
+    collection: Collection<Transaction>,
+    deployments: Collection<ContractDeployment>,
+    /// Whether receipts are fetched to fill the receipt fields. Without them only the receipts of
+    /// contract creations are fetched, to record the deployments.
+    receipts: bool,
+}
+
+impl TransactionHandler {
+    pub fn new(db: &MongoDb, config: &Config) -> Result<TransactionHandler> {
+        let collection = db.database.collection::<Transaction>(&config.collection(NAME));
+        database::create_unique_index(&collection, "hash")?;
+        let deployments = db.database.collection::<ContractDeployment>(DEPLOYMENTS_COLLECTION);
+        deployments.create_index(IndexModel::builder().keys(doc! {"address": 1u32}).options(IndexOptions::builder().unique(true).build()).build(), None)?;
+        deployments.create_index(IndexModel::builder().keys(doc! {"deployer": 1u32}).build(), None)?;
+        let receipts = config.importer(NAME).receipts.unwrap_or(false);
+        Ok(TransactionHandler { collection, deployments, receipts })
+    }
+}
+
+impl BlockHandler for TransactionHandler {
+    type Document = Transaction;
+
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn label(&self) -> &str {
+        "Transactions"
+    }
+
+    fn fetch(&self) -> Fetch {
+        if self.receipts {
+            Fetch::Receipts
+        } else {
+            Fetch::Deployments
+        }
+    }
+
+    fn decode(&mut self, block: &BlockData) -> Result<Vec<Transaction>> {
+        let ts = block.timestamp.unwrap_or_default().as_u64() * 1000;
+        let receipts: HashMap<H256, &TransactionReceipt> = block.receipts.iter().map(|receipt| (receipt.transaction_hash, receipt)).collect();
+        let mut tx_pool = vec![];
+        for tx in &block.transactions {
+            let receipt = receipts.get(&tx.hash).copied();
+            let from = str::replace(&web3::helpers::to_string(&tx.from), "\"", "");
+            let hash = str::replace(&web3::helpers::to_string(&tx.hash), "\"", "");
+            let contract_address = receipt.and_then(|receipt| receipt.contract_address).map(|address| str::replace(&web3::helpers::to_string(&address), "\"", ""));
+            // Creation receipts are always fetched, the receipt fields are only filled with `receipts`.
+            let receipt = receipt.filter(|_| self.receipts);
+            let deployment = match (tx.to, &contract_address) {
+                (None, Some(address)) => Some(ContractDeployment {
+                    address: address.clone(),
+                    deployer: from.clone(),
+                    block: block.number.as_u32(),
+                    transaction_id: hash.clone(),
+                    created_at: DateTime::from_millis(ts as i64),
+                    bytecode_hash: web3::helpers::to_string(&H256::from(keccak256(&tx.input.0))).replace('"', ""),
+                }),
+                _ => None
+            };
+            tx_pool.push(Transaction {
+                from,
+                to: tx.to.map(|to| str::replace(&web3::helpers::to_string(&to), "\"", "")),
+                hash,
+                block: block.number.as_u32(),
+                created_at: DateTime::from_millis(ts as i64),
+                transaction_index: tx.transaction_index.map(|index| index.as_u32()),
+                nonce: tx.nonce.low_u64(),
+                value: tx.value.to_string(),
+                gas: tx.gas.low_u64(),
+                gas_price: tx.gas_price.to_string(),
+                input: str::replace(&web3::helpers::to_string(&tx.input), "\"", ""),
+                status: receipt.and_then(|receipt| receipt.status).map(|status| status.as_u32()),
+                gas_used: receipt.and_then(|receipt| receipt.gas_used).map(|gas| gas.low_u64()),
+                contract_address: contract_address.filter(|_| self.receipts),
+                log_count: receipt.map(|receipt| receipt.logs.len() as u32),
+                deployment,
+            });
+        }
+        Ok(tx_pool)
+    }
+
+    fn collection(&self) -> &Collection<Transaction> {
+        &self.collection
+    }
+
+    fn persist(&self, db: &MongoDb, documents: Vec<Transaction>) -> Result<WriteSummary> {
+        let deployments: Vec<ContractDeployment> = documents.iter().filter_map(|tx| tx.deployment.clone()).collect();
+        if !deployments.is_empty() {
+            db.upsert_many(&self.deployments, "address", deployments)?;
+        }
+        database::insert_unordered(&self.collection, documents)
+    }
+
+    fn rollback(&self, block: U64) -> Result<()> {
+        self.collection.delete_many(doc! {"block": {"$gt": block.as_u64() as i64}}, None)?;
+        self.deployments.delete_many(doc! {"block": {"$gt": block.as_u64() as i64}}, None)?;
+        Ok(())
+    }
+}
+
+/// Imports all transactions.
+pub async fn run(config: &Config) -> Result<()> {
+    importers::scan(config, &[NAME]).await
+}
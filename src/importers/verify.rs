@@ -0,0 +1,107 @@
+use web3::contract::{Contract, Options};
+use web3::types::Address;
+
+use crate::config::Config;
+use crate::contracts::contracts::{self, ContractType};
+use crate::error::{Error, Result};
+use crate::importers::erc_transfers;
+use crate::scanner::{self, Transport};
+use crate::tools::database::MongoDb;
+
+/// ERC20 metadata and ERC165, enough to tell what a contract is.
+const ABI: &[u8] = br#"[
+    {"type": "function", "name": "name", "inputs": [], "outputs": [{"name": "", "type": "string"}], "stateMutability": "view"},
+    {"type": "function", "name": "symbol", "inputs": [], "outputs": [{"name": "", "type": "string"}], "stateMutability": "view"},
+    {"type": "function", "name": "decimals", "inputs": [], "outputs": [{"name": "", "type": "uint8"}], "stateMutability": "view"},
+    {"type": "function", "name": "supportsInterface", "inputs": [{"name": "interfaceId", "type": "bytes4"}], "outputs": [{"name": "", "type": "bool"}], "stateMutability": "view"}
+]"#;
+
+/// ERC165 interface id of ERC721.
+const ERC721_INTERFACE: [u8; 4] = [0x80, 0xac, 0x58, 0xcd];
+/// ERC165 interface id of ERC1155.
+const ERC1155_INTERFACE: [u8; 4] = [0xd9, 0xb6, 0x7a, 0x26];
+
+/// Metadata reported by a contract. Calls the contract does not implement are left empty.
+struct Metadata {
+    name: Option<String>,
+    symbol: Option<String>,
+    decimals: Option<u8>,
+    erc: ContractType,
+}
+
+/// Result of a call the contract may not implement. Reverts and undecodable results mean it does
+/// not, failures to reach the node are passed on so they are not mistaken for a mismatch.
+fn implemented<T>(result: std::result::Result<T, web3::contract::Error>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(web3::contract::Error::Api(error @ (web3::Error::Transport(_) | web3::Error::Io(_) | web3::Error::Unreachable))) => Err(error.into()),
+        Err(_) => Ok(None)
+    }
+}
+
+async fn metadata(contract: &Contract<Transport>) -> Result<Metadata> {
+    let name = implemented(contract.query("name", (), None, Options::default(), None).await)?;
+    let symbol = implemented(contract.query("symbol", (), None, Options::default(), None).await)?;
+    let decimals = implemented(contract.query("decimals", (), None, Options::default(), None).await)?;
+    let erc721: Option<bool> = implemented(contract.query("supportsInterface", (ERC721_INTERFACE,), None, Options::default(), None).await)?;
+    let erc1155: Option<bool> = implemented(contract.query("supportsInterface", (ERC1155_INTERFACE,), None, Options::default(), None).await)?;
+
+    let erc = match (erc721, erc1155, decimals) {
+        (Some(true), _, _) => ContractType::ERC721,
+        (_, Some(true), _) => ContractType::ERC1155,
+        (_, _, Some(_)) => ContractType::ERC20,
+        _ => ContractType::Unknown
+    };
+    Ok(Metadata { name, symbol, decimals, erc })
+}
+
+/// Compares the registered name, decimals and type of every known contract with what the contract
+/// reports on chain. Fails if any of them differ.
+pub async fn run(config: &Config) -> Result<()> {
+    let web3 = scanner::connect(&config.web3.hostnames, &config.web3.provider_type, config.transport_options()).await?;
+    let db = MongoDb::new(config.database_options(erc_transfers::NAME)).await?;
+    let registry = contracts::load(&config.contracts, &db)?;
+
+    let mut addresses: Vec<&String> = registry.keys().collect();
+    addresses.sort();
+
+    println!("{:<44}{:<8}{:>10}{:>8}  On chain", "Contract", "Name", "Decimals", "Type");
+    let mut mismatches = 0;
+    for address in addresses {
+        let registered = &registry[address];
+        let parsed: Address = address.parse().map_err(|_| Error::Config(format!("Invalid contract address {}", address)))?;
+        let contract = Contract::from_json(web3.eth(), parsed, ABI).map_err(|error| Error::Config(error.to_string()))?;
+        let actual = metadata(&contract).await?;
+
+        let mut problems = vec![];
+        if actual.symbol.as_deref() != Some(registered.name.as_str()) {
+            problems.push(format!("symbol {}", actual.symbol.as_deref().unwrap_or("-")));
+        }
+        if actual.erc != registered.erc {
+            problems.push(format!("type {:?}", actual.erc));
+        }
+        if let (ContractType::ERC20, Some(decimals)) = (registered.erc, actual.decimals) {
+            if decimals as usize != registered.decimals {
+                problems.push(format!("decimals {}", decimals));
+            }
+        }
+
+        let status = if problems.is_empty() {
+            "OK".to_string()
+        } else {
+            mismatches += 1;
+            format!("MISMATCH: {}", problems.join(", "))
+        };
+        println!(
+            "{:<44}{:<8}{:>10}{:>8}  {} ({}) {}",
+            address, registered.name, registered.decimals, format!("{:?}", registered.erc),
+            actual.name.as_deref().unwrap_or("-"), actual.symbol.as_deref().unwrap_or("-"), status
+        );
+    }
+
+    if mismatches > 0 {
+        return Err(Error::Config(format!("{} registered contracts do not match the chain", mismatches)));
+    }
+    println!("All registered contracts match the chain");
+    Ok(())
+}
@@ -0,0 +1,11 @@
+#[macro_use]
+extern crate fstrings;
+
+pub mod cache;
+pub mod config;
+pub mod contracts;
+pub mod error;
+pub mod importers;
+pub mod scanner;
+pub mod tools;
+pub mod transport;
@@ -0,0 +1,130 @@
+use std::process::ExitCode;
+
+use clap::builder::{PossibleValue, PossibleValuesParser};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+
+use ronin_to_mongo::error::{self, Result};
+use ronin_to_mongo::importers::{self, analyse, axie_transfers, blocks, erc_transfers, leaderboard, sales, stats, transactions, verify};
+use ronin_to_mongo::scanner::CommonArgs;
+
+/// Ronin blockchain importer for MongoDB
+#[derive(Parser, Debug)]
+#[clap(name = "ronin-importer", author, version, about, long_about = None)]
+struct Cli {
+    #[clap(flatten)]
+    common: CommonArgs,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Import the header of every block
+    Blocks,
+    /// Import all transactions
+    Transactions,
+    /// Import the transfers of the axie contract
+    AxieTransfers,
+    /// Import the axie sales of the marketplace
+    Sales,
+    /// Import the transfers of all known ERC20, ERC721 and ERC1155 contracts
+    ErcTransfers,
+    /// Import the transfers of newly registered contracts up to the erc-transfer checkpoint
+    BackfillContracts {
+        /// Addresses of the registered contracts to backfill
+        #[clap(value_parser, required = true)]
+        addresses: Vec<String>,
+    },
+    /// Rewrite the creation date of imported token transfers to the timestamp of their block
+    RepairTimestamps,
+    /// Import the number of transactions per block
+    Stats,
+    /// Import the battle logs of the top players of the leaderboard
+    Leaderboard,
+    /// Analyse the imported battle logs
+    Analyse,
+    /// Run several block importers in a single pass over the chain
+    Run {
+        /// Importers to run by subcommand (service names are accepted too), each resumes from its own checkpoint
+        #[clap(
+            value_parser = PossibleValuesParser::new(importers::BLOCK_SUBCOMMANDS.iter().zip(importers::BLOCK_IMPORTERS).map(|(command, name)| PossibleValue::new(command).alias(name))),
+            default_values = &importers::BLOCK_SUBCOMMANDS
+        )]
+        importers: Vec<String>,
+    },
+    /// Show the collection and checkpoint of every importer
+    List,
+    /// Compare the registered token contracts with their metadata on chain
+    VerifyContracts,
+    /// Print shell completions
+    Completions {
+        #[clap(value_parser)]
+        shell: Shell,
+    },
+}
+
+impl Command {
+    /// What the command imports, `None` for commands that do not import anything.
+    fn imports(&self) -> Option<&'static str> {
+        match self {
+            Command::Blocks => Some("blocks"),
+            Command::Transactions => Some("transactions"),
+            Command::AxieTransfers => Some("axie transfers"),
+            Command::Sales => Some("axie sales"),
+            Command::ErcTransfers | Command::BackfillContracts { .. } => Some("token transfers"),
+            Command::Stats => Some("block statistics"),
+            Command::Leaderboard => Some("battle logs"),
+            Command::Run { .. } => Some("blocks"),
+            Command::Analyse | Command::RepairTimestamps | Command::List | Command::VerifyContracts | Command::Completions { .. } => None
+        }
+    }
+}
+
+async fn run(cli: &Cli) -> Result<()> {
+    let common = &cli.common;
+    match &cli.command {
+        Command::Blocks => blocks::run(&common.config(blocks::NAME)?).await,
+        Command::Transactions => transactions::run(&common.config(transactions::NAME)?).await,
+        Command::AxieTransfers => axie_transfers::run(&common.config(axie_transfers::NAME)?).await,
+        Command::Sales => sales::run(&common.config(sales::NAME)?).await,
+        Command::ErcTransfers => erc_transfers::run(&common.config(erc_transfers::NAME)?).await,
+        Command::BackfillContracts { addresses } => erc_transfers::backfill(&common.config(erc_transfers::NAME)?, addresses).await,
+        Command::RepairTimestamps => erc_transfers::repair_timestamps(&common.config(erc_transfers::NAME)?).await,
+        Command::Stats => stats::run(&common.config(stats::NAME)?).await,
+        Command::Leaderboard => leaderboard::run(&common.config(leaderboard::NAME)?).await,
+        Command::Analyse => analyse::run(&common.config(leaderboard::NAME)?).await,
+        Command::Run { importers } => {
+            let names: Vec<&str> = importers.iter().filter_map(|command| importers::service_name(command)).collect();
+            importers::scan(&common.importers_config(&names)?, &names).await
+        }
+        Command::List => importers::list(&common.global_config()?).await,
+        Command::VerifyContracts => verify::run(&common.global_config()?).await,
+        Command::Completions { shell } => {
+            clap_complete::generate(*shell, &mut Cli::command(), "ronin-importer", &mut std::io::stdout());
+            Ok(())
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    const VERSION: &str = env!("CARGO_PKG_VERSION");
+    const NAME: &str = env!("CARGO_PKG_NAME");
+
+    let cli: Cli = Cli::parse();
+
+    match cli.command.imports() {
+        Some(what) => {
+            println!("{} v{}", NAME, VERSION);
+            error::report(run(&cli).await, what)
+        }
+        None => match run(&cli).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("{}", error);
+                error.exit_code()
+            }
+        }
+    }
+}
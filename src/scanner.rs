@@ -0,0 +1,1117 @@
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Args;
+use futures::future::{try_join_all, FutureExt, LocalBoxFuture};
+use futures::stream::{self, StreamExt};
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::options::FindOneOptions;
+use mongodb::sync::Collection;
+use serde::Serialize;
+use sha2::digest::Update;
+use sha2::{Digest, Sha256};
+use web3::transports::WebSocket;
+use web3::api::SubscriptionStream;
+use web3::types::{Address, BlockHeader, BlockId, BlockNumber, FilterBuilder, Log, Transaction, TransactionReceipt, H256, U256, U64};
+use web3::{BatchTransport, Transport as _, Web3};
+
+use crate::cache::{Header, HeaderCache};
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::tools::database::{self, MongoDb, WriteSummary};
+use crate::transport::{is_rate_limit, ResilientTransport, TransportOptions};
+
+/// Transport shared by all importers. Selected at runtime through `--web3-provider-type`.
+pub type Transport = ResilientTransport;
+
+/// Command line options shared by every importer. Each option can also be set through the listed
+/// `RONIN_*` environment variable and overrides the config file.
+#[derive(Args, Debug)]
+pub struct CommonArgs {
+    /// Config file - TOML, see config.example.toml
+    #[clap(long, global = true, value_parser, env = "RONIN_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// MongoDB connection URL [default: mongodb://127.0.0.1:27017]
+    #[clap(long, global = true, value_parser, env = "RONIN_MONGODB_URI")]
+    pub mongodb_uri: Option<String>,
+    /// MongoDB database name [default: ronin]
+    #[clap(long, global = true, value_parser, env = "RONIN_MONGODB_NAME")]
+    pub mongodb_name: Option<String>,
+    /// MongoDB collection name [default: depends on the importer]
+    #[clap(long, global = true, value_parser, env = "RONIN_MONGODB_COLLECTION")]
+    pub mongodb_collection: Option<String>,
+    /// Web3 Hosts - Comma separated, requests are spread over all healthy hosts [default: ws://localhost:8546]
+    #[clap(long, global = true, value_parser, value_delimiter = ',', env = "RONIN_WEB3_HOSTNAME")]
+    pub web3_hostname: Option<Vec<String>>,
+    /// Web3 Provider Type - Used for hosts without ws:// or http:// scheme [default: ws]
+    #[clap(long, global = true, value_parser, env = "RONIN_WEB3_PROVIDER_TYPE")]
+    pub web3_provider_type: Option<String>,
+    /// Start Block - Set to 0 to resume from the last checkpoint [default: depends on the importer]
+    #[clap(long, global = true, value_parser, env = "RONIN_START_BLOCK")]
+    pub start_block: Option<u32>,
+    /// End Block - If set to anything but 0 the import will stop at this block. [default: 0]
+    #[clap(long, global = true, value_parser, env = "RONIN_END_BLOCK")]
+    pub end_block: Option<u32>,
+    /// Number of documents collected before they are written to the database [default: depends on the importer]
+    #[clap(long, global = true, value_parser, env = "RONIN_BATCH_SIZE")]
+    pub batch_size: Option<usize>,
+    /// Maximum number of blocks covered by a single log query [default: 10000]
+    #[clap(long, global = true, value_parser, env = "RONIN_MAX_WINDOW")]
+    pub max_window: Option<u64>,
+    /// Number of blocks (or log ranges) fetched concurrently [default: 4]
+    #[clap(long, global = true, value_parser, env = "RONIN_CONCURRENCY")]
+    pub concurrency: Option<usize>,
+    /// Number of blocks to stay behind the chain head, so only blocks that will not be reorged are imported [default: 0]
+    #[clap(long, global = true, value_parser, env = "RONIN_CONFIRMATIONS")]
+    pub confirmations: Option<u64>,
+    /// Keep importing new blocks after catching up with the chain head
+    #[clap(long, global = true, env = "RONIN_FOLLOW")]
+    pub follow: bool,
+    /// Also store status, gas used, created contract and log count of every transaction (transactions importer only)
+    #[clap(long, global = true, env = "RONIN_RECEIPTS")]
+    pub receipts: bool,
+    /// Number of block headers kept in memory to resolve block timestamps [default: 10000]
+    #[clap(long, global = true, value_parser, env = "RONIN_HEADER_CACHE")]
+    pub header_cache: Option<usize>,
+    /// Look up block headers in the collection of the blocks importer before asking the node
+    #[clap(long, global = true, env = "RONIN_HEADERS_FROM_DB")]
+    pub headers_from_db: bool,
+    /// Token contract registry - TOML or JSON file, see contracts.example.toml [default: built-in]
+    #[clap(long, global = true, value_parser, env = "RONIN_CONTRACTS_FILE")]
+    pub contracts_file: Option<PathBuf>,
+    /// Collection holding the token contract registry [default: built-in]
+    #[clap(long, global = true, value_parser, env = "RONIN_CONTRACTS_COLLECTION")]
+    pub contracts_collection: Option<String>,
+    /// Number of retries for failed RPC calls before the import is aborted [default: 5]
+    #[clap(long, global = true, value_parser, env = "RONIN_RPC_RETRIES")]
+    pub rpc_retries: Option<u32>,
+    /// Delay before the first retry of a failed RPC call in milliseconds, doubled on every retry [default: 500]
+    #[clap(long, global = true, value_parser, env = "RONIN_RPC_BACKOFF")]
+    pub rpc_backoff: Option<u64>,
+    /// Number of blocks a host may fall behind the best host before it is taken out of rotation [default: 10]
+    #[clap(long, global = true, value_parser, env = "RONIN_MAX_LAG")]
+    pub max_lag: Option<u64>,
+}
+
+impl CommonArgs {
+    /// Loads the config file and applies the global options given on the command line or through
+    /// the environment.
+    pub fn global_config(&self) -> Result<Config> {
+        let mut config = Config::load(self.config.as_deref())?;
+
+        if let Some(uri) = &self.mongodb_uri {
+            config.mongodb.uri = uri.clone();
+        }
+        if let Some(name) = &self.mongodb_name {
+            config.mongodb.name = name.clone();
+        }
+        if let Some(hostnames) = &self.web3_hostname {
+            config.web3.hostnames = hostnames.clone();
+        }
+        if let Some(provider_type) = &self.web3_provider_type {
+            config.web3.provider_type = provider_type.clone();
+        }
+        config.web3.rpc_retries = self.rpc_retries.unwrap_or(config.web3.rpc_retries);
+        config.web3.rpc_backoff = self.rpc_backoff.unwrap_or(config.web3.rpc_backoff);
+        config.web3.max_lag = self.max_lag.unwrap_or(config.web3.max_lag);
+        config.scanner.end_block = self.end_block.unwrap_or(config.scanner.end_block);
+        config.scanner.max_window = self.max_window.unwrap_or(config.scanner.max_window);
+        config.scanner.concurrency = self.concurrency.unwrap_or(config.scanner.concurrency);
+        config.scanner.follow |= self.follow;
+        config.scanner.header_cache = self.header_cache.unwrap_or(config.scanner.header_cache);
+        config.scanner.headers_from_db |= self.headers_from_db;
+        if let Some(file) = &self.contracts_file {
+            config.contracts.file = Some(file.clone());
+        }
+        if let Some(collection) = &self.contracts_collection {
+            config.contracts.collection = Some(collection.clone());
+        }
+
+        Ok(config)
+    }
+
+    /// Like [`CommonArgs::global_config`], additionally applying the importer specific options to
+    /// the section of importer `name`.
+    pub fn config(&self, name: &str) -> Result<Config> {
+        self.importers_config(&[name])
+    }
+
+    /// Like [`CommonArgs::config`] for several importers sharing one scan. `--mongodb-collection`
+    /// names a single collection and is only applied if there is a single importer. The same goes
+    /// for `--mongodb-name`, otherwise it only replaces the global database, so importers with a
+    /// database of their own (block statistics) keep it.
+    pub fn importers_config(&self, names: &[&str]) -> Result<Config> {
+        let mut config = self.global_config()?;
+
+        for name in names {
+            let importer = config.importer_mut(name);
+            if let (Some(name), 1) = (&self.mongodb_name, names.len()) {
+                importer.database = Some(name.clone());
+            }
+            if let (Some(collection), 1) = (&self.mongodb_collection, names.len()) {
+                importer.collection = Some(collection.clone());
+            }
+            importer.start_block = self.start_block.or(importer.start_block);
+            importer.batch_size = self.batch_size.or(importer.batch_size);
+            importer.confirmations = self.confirmations.or(importer.confirmations);
+            if self.receipts {
+                importer.receipts = Some(true);
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+pub async fn connect(hostnames: &[String], provider_type: &str, options: TransportOptions) -> Result<Web3<Transport>> {
+    Ok(Web3::new(ResilientTransport::new(hostnames, provider_type, options).await?))
+}
+
+/// Returns the highest block stored in a collection whose documents carry a numeric `block` field.
+pub fn get_db_head_block<T>(col: &Collection<T>) -> Result<Option<U64>> {
+    let col = col.clone_with_type::<Document>();
+    let options = FindOneOptions::builder().sort(doc! {"block": -1i64}).build();
+    let result = match col.find_one(None, options)? {
+        Some(result) => result,
+        None => return Ok(None)
+    };
+    Ok(match result.get("block") {
+        Some(Bson::Int32(block)) => Some(U64::from(*block as u64)),
+        Some(Bson::Int64(block)) => Some(U64::from(*block as u64)),
+        _ => None
+    })
+}
+
+/// Describes which data the scanner has to fetch from the node for every block.
+/// Logs are queried for whole block ranges, everything else block by block.
+pub enum Fetch {
+    /// The block header, including the transaction hashes.
+    Header,
+    /// The block including all transaction objects.
+    Transactions,
+    /// The block including all transaction objects and their receipts.
+    Receipts,
+    /// The block including all transaction objects, plus the receipts of contract creations.
+    Deployments,
+    /// All logs matching any of the filters.
+    Logs(Vec<LogFilter>),
+}
+
+/// Matches logs emitted by one of `addresses` whose first topic is one of `topics`. An empty
+/// list matches everything.
+#[derive(Clone)]
+pub struct LogFilter {
+    pub addresses: Vec<Address>,
+    pub topics: Vec<H256>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, log: &Log) -> bool {
+        (self.addresses.is_empty() || self.addresses.contains(&log.address))
+            && (self.topics.is_empty() || log.topics.first().is_some_and(|topic| self.topics.contains(topic)))
+    }
+}
+
+/// Data fetched for a single block, handed to [`BlockHandler::decode`].
+pub struct BlockData {
+    pub number: U64,
+    pub hash: H256,
+    pub parent_hash: H256,
+    /// Block timestamp in seconds.
+    pub timestamp: Option<U256>,
+    /// Address of the validator that sealed the block.
+    pub miner: Address,
+    pub gas_used: U256,
+    pub gas_limit: U256,
+    /// Block size in bytes.
+    pub size: Option<U256>,
+    pub transaction_count: usize,
+    pub transactions: Vec<Transaction>,
+    /// Receipts of `transactions`, in the same order. All of them for `Fetch::Receipts`, only
+    /// those of contract creations for `Fetch::Deployments`.
+    pub receipts: Vec<TransactionReceipt>,
+    pub logs: Vec<Log>,
+}
+
+/// Unique id of a log, derived from its transaction hash and log index.
+pub fn log_id(log: &Log) -> String {
+    let hash = web3::helpers::to_string(&log.transaction_hash.unwrap_or_default());
+    let index = web3::helpers::to_string(&log.log_index.unwrap_or_default());
+    let mut hasher = Sha256::new();
+    Update::update(&mut hasher, format!("{}-{}", hash, index).as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+impl BlockData {
+    fn empty(number: U64) -> BlockData {
+        BlockData {
+            number,
+            hash: H256::zero(),
+            parent_hash: H256::zero(),
+            timestamp: None,
+            miner: Address::zero(),
+            gas_used: U256::zero(),
+            gas_limit: U256::zero(),
+            size: None,
+            transaction_count: 0,
+            transactions: vec![],
+            receipts: vec![],
+            logs: vec![],
+        }
+    }
+
+    fn set_header(&mut self, header: &Header) {
+        self.hash = header.hash;
+        self.parent_hash = header.parent_hash;
+        self.timestamp = Some(header.timestamp);
+        self.miner = header.miner;
+        self.gas_used = header.gas_used;
+        self.gas_limit = header.gas_limit;
+        self.size = header.size;
+        self.transaction_count = header.transaction_count;
+    }
+
+    /// The part of this block an importer asked for with `fetch`, used when importers share a scan.
+    fn select(&self, fetch: &Fetch) -> BlockData {
+        BlockData {
+            number: self.number,
+            hash: self.hash,
+            parent_hash: self.parent_hash,
+            timestamp: self.timestamp,
+            miner: self.miner,
+            gas_used: self.gas_used,
+            gas_limit: self.gas_limit,
+            size: self.size,
+            transaction_count: self.transaction_count,
+            transactions: match fetch {
+                Fetch::Transactions | Fetch::Receipts | Fetch::Deployments => self.transactions.clone(),
+                _ => vec![]
+            },
+            receipts: match fetch {
+                Fetch::Receipts => self.receipts.clone(),
+                Fetch::Deployments => {
+                    let creations: Vec<H256> = self.transactions.iter().filter(|tx| tx.to.is_none()).map(|tx| tx.hash).collect();
+                    self.receipts.iter().filter(|receipt| creations.contains(&receipt.transaction_hash)).cloned().collect()
+                }
+                _ => vec![]
+            },
+            logs: match fetch {
+                Fetch::Logs(filters) => self.logs.iter().filter(|log| filters.iter().any(|filter| filter.matches(log))).cloned().collect(),
+                _ => vec![]
+            },
+        }
+    }
+}
+
+/// An importer plugged into the [`Scanner`]: it declares what to fetch, decodes the fetched
+/// block into documents and names the collection they are persisted to.
+pub trait BlockHandler {
+    type Document: Serialize;
+
+    /// Service name used for the health and checkpoint entries, e.g. `transactions`.
+    fn name(&self) -> &str;
+
+    /// Label used for the per-block progress line, e.g. `Transactions`.
+    fn label(&self) -> &str;
+
+    fn fetch(&self) -> Fetch;
+
+    /// Fails with [`Error::Decode`] pointing at the offending block or log.
+    fn decode(&mut self, block: &BlockData) -> Result<Vec<Self::Document>>;
+
+    fn collection(&self) -> &Collection<Self::Document>;
+
+    /// Field that uniquely identifies a document, e.g. `log_id`. Documents with a key are upserted,
+    /// so importing a range twice replaces them instead of adding copies.
+    fn key(&self) -> Option<&str> {
+        None
+    }
+
+    /// Writes a batch of decoded documents. Documents that were imported before are skipped.
+    fn persist(&self, db: &MongoDb, documents: Vec<Self::Document>) -> Result<WriteSummary> {
+        match self.key() {
+            Some(key) => db.upsert_many(self.collection(), key, documents),
+            None => database::insert_unordered(self.collection(), documents)
+        }
+    }
+
+    /// Removes everything imported after `block` once a chain reorganisation orphaned those blocks.
+    /// Handlers writing to more than one collection have to clean up all of them.
+    fn rollback(&self, block: U64) -> Result<()> {
+        self.collection().delete_many(doc! {"block": {"$gt": block.as_u64() as i64}}, None)?;
+        Ok(())
+    }
+}
+
+pub struct ScannerOptions {
+    /// Start Block - Set to 0 to resume from the last checkpoint
+    pub start_block: u32,
+    /// End Block - If set to anything but 0 the import will stop at this block.
+    pub end_block: u32,
+    /// Number of blocks to stay behind the chain head.
+    pub confirmations: u64,
+    /// Number of documents collected before they are written to the database.
+    pub batch_size: usize,
+    /// Upper bound for the number of blocks covered by a single log query.
+    pub max_window: u64,
+    /// Number of logs per query the window is grown towards.
+    pub target_logs: usize,
+    /// Number of blocks (or log ranges) fetched concurrently.
+    pub concurrency: usize,
+    /// Number of recently processed block hashes kept to detect chain reorganisations.
+    pub reorg_depth: usize,
+    /// Keep importing new blocks once the chain head is reached. Ignored if `end_block` is set.
+    pub follow: bool,
+    /// How often the chain head is polled while following over HTTP.
+    pub poll_interval: Duration,
+}
+
+impl Default for ScannerOptions {
+    fn default() -> Self {
+        ScannerOptions {
+            start_block: 1,
+            end_block: 0,
+            confirmations: 0,
+            batch_size: 1,
+            max_window: 10000,
+            target_logs: 2000,
+            concurrency: 4,
+            reorg_depth: 256,
+            follow: false,
+            poll_interval: Duration::from_secs(3),
+        }
+    }
+}
+
+/// Returns true if the node refused a log query because the range or its response was too large.
+/// Providers use the same error code (-32005) for rate limits, which are retried by the transport
+/// instead, so only the message decides.
+fn is_range_too_large(error: &web3::Error) -> bool {
+    const HINTS: [&str; 7] = [
+        "query returned more than",
+        "response size exceeded",
+        "response size should not",
+        "block range is too",
+        "block range too",
+        "exceed maximum block range",
+        "range limit exceeded",
+    ];
+    match error {
+        web3::Error::Rpc(error) => {
+            let message = error.message.to_lowercase();
+            !is_rate_limit(error) && HINTS.iter().any(|hint| message.contains(hint))
+        }
+        _ => false
+    }
+}
+
+/// Blocks fetched for one range, plus the block hashes needed to check that the range extends
+/// the chain seen so far.
+struct Range {
+    from: U64,
+    to: U64,
+    blocks: Vec<BlockData>,
+    /// Parent hash of `from`.
+    parent_hash: H256,
+    /// Hashes of the blocks in this range whose header was fetched, in ascending order. Always
+    /// contains `from` and `to`.
+    hashes: Vec<(U64, H256)>,
+}
+
+impl Range {
+    fn append(&mut self, mut other: Range) {
+        self.to = other.to;
+        self.blocks.append(&mut other.blocks);
+        self.hashes.append(&mut other.hashes);
+    }
+}
+
+fn missing_block(block: U64) -> web3::Error {
+    web3::Error::InvalidResponse(format!("Block {} not found", block))
+}
+
+fn missing_receipt(tx: &Transaction, receipt: Option<TransactionReceipt>) -> web3::Result<TransactionReceipt> {
+    receipt.ok_or_else(|| web3::Error::InvalidResponse(format!("Missing receipt of transaction {:?}", tx.hash)))
+}
+
+/// What a scan fetches to serve all of its importers: headers or full blocks if any importer
+/// needs them, and the logs matching any of their filters.
+struct Plan {
+    /// `Fetch::Header`, `Fetch::Transactions`, `Fetch::Deployments` or `Fetch::Receipts`. `None` if only logs are needed, in which case
+    /// logs are queried for whole ranges.
+    blocks: Option<Fetch>,
+    logs: Vec<LogFilter>,
+}
+
+impl Plan {
+    fn new<'a>(fetches: impl IntoIterator<Item = &'a Fetch>) -> Plan {
+        let mut plan = Plan { blocks: None, logs: vec![] };
+        for fetch in fetches {
+            match fetch {
+                Fetch::Receipts => plan.blocks = Some(Fetch::Receipts),
+                Fetch::Deployments => {
+                    if !matches!(plan.blocks, Some(Fetch::Receipts)) {
+                        plan.blocks = Some(Fetch::Deployments);
+                    }
+                }
+                Fetch::Transactions => {
+                    if !matches!(plan.blocks, Some(Fetch::Receipts | Fetch::Deployments)) {
+                        plan.blocks = Some(Fetch::Transactions);
+                    }
+                }
+                Fetch::Header => {
+                    if plan.blocks.is_none() {
+                        plan.blocks = Some(Fetch::Header);
+                    }
+                }
+                Fetch::Logs(filters) => plan.logs.extend(filters.iter().cloned())
+            }
+        }
+        plan
+    }
+}
+
+/// Fetches block data from the node. Kept apart from the handlers so that several ranges can be
+/// in flight while the handlers decode and persist the ones that already arrived.
+struct Fetcher {
+    web3: Web3<Transport>,
+    /// Current number of blocks per log query. Adapted to the density of the fetched logs.
+    window: Cell<u64>,
+    /// Whether the node supports `eth_getBlockReceipts`, until it says otherwise.
+    block_receipts: Cell<bool>,
+    headers: HeaderCache,
+}
+
+impl Fetcher {
+    /// Fetches all blocks from `from` to `to` (inclusive). Log-only plans only return blocks containing logs.
+    async fn fetch_range(&self, plan: &Plan, from: U64, to: U64) -> web3::Result<Range> {
+        match &plan.blocks {
+            None => self.fetch_logs(&plan.logs, from, to).await,
+            Some(fetch) => {
+                let mut blocks = vec![];
+                let mut block = from;
+                while block <= to {
+                    let mut data = self.fetch_block(fetch, block).await?;
+                    // A single query for all logs of the block, importers only see the logs matching their filters.
+                    if !plan.logs.is_empty() {
+                        let filter = FilterBuilder::default().block_hash(data.hash).build();
+                        data.logs = self.web3.eth().logs(filter).await?;
+                        data.logs.sort_by_key(|log| log.log_index);
+                    }
+                    blocks.push(data);
+                    block = block + 1;
+                }
+                Ok(Range {
+                    from,
+                    to,
+                    parent_hash: blocks[0].parent_hash,
+                    hashes: blocks.iter().map(|data| (data.number, data.hash)).collect(),
+                    blocks,
+                })
+            }
+        }
+    }
+
+    /// Queries the logs of a range, splitting it in halves for as long as the node rejects it as too large.
+    fn fetch_logs<'a>(&'a self, filters: &'a [LogFilter], from: U64, to: U64) -> LocalBoxFuture<'a, web3::Result<Range>> {
+        async move {
+            match self.query_logs(filters, from, to).await {
+                Err(error) if to > from && is_range_too_large(&error) => {
+                    let mid = from + (to - from) / 2;
+                    self.window.set(std::cmp::max(std::cmp::min(self.window.get(), (to - from).as_u64() / 2), 1));
+                    println!("Range {} - {} rejected by node, shrinking window to {} blocks", from, to, self.window.get());
+                    let mut range = self.fetch_logs(filters, from, mid).await?;
+                    range.append(self.fetch_logs(filters, mid + 1, to).await?);
+                    Ok(range)
+                }
+                result => result
+            }
+        }.boxed_local()
+    }
+
+    /// All logs from `from` to `to` matching any of `filters`, ordered by block and log index.
+    /// Logs matching several filters are only returned once.
+    async fn logs(&self, filters: &[LogFilter], from: U64, to: U64) -> web3::Result<Vec<Log>> {
+        let mut logs = vec![];
+        for LogFilter { addresses, topics } in filters {
+            let filter = FilterBuilder::default()
+                .from_block(BlockNumber::from(from))
+                .to_block(BlockNumber::from(to))
+                .address(addresses.clone())
+                .topics(Some(topics.clone()), None, None, None)
+                .build();
+            logs.append(&mut self.web3.eth().logs(filter).await?);
+        }
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+        logs.dedup_by_key(|log| (log.block_number, log.log_index));
+        Ok(logs)
+    }
+
+    async fn query_logs(&self, filters: &[LogFilter], from: U64, to: U64) -> web3::Result<Range> {
+        let mut logs: BTreeMap<U64, Vec<Log>> = BTreeMap::new();
+        for log in self.logs(filters, from, to).await? {
+            logs.entry(log.block_number.unwrap_or_default()).or_default().push(log);
+        }
+
+        // The range boundaries are always fetched from the node to link the range to the previous
+        // one, the headers of the blocks with logs may come from the cache.
+        let mut boundaries = vec![from];
+        if to != from {
+            boundaries.push(to);
+        }
+        let boundaries = try_join_all(boundaries.into_iter().map(|number| async move {
+            let header = self.web3.eth().block(BlockId::Number(BlockNumber::from(number))).await?;
+            let header = Header::from(&header.ok_or_else(|| missing_block(number))?);
+            self.headers.insert(header.clone());
+            Ok::<_, web3::Error>(header)
+        })).await?;
+        let numbers: Vec<U64> = logs.keys().copied().filter(|number| *number != from && *number != to).collect();
+
+        let mut headers: BTreeMap<U64, Header> = boundaries.into_iter().map(|header| (header.number, header)).collect();
+        headers.extend(self.headers.headers(&numbers).await?.into_iter().map(|header| (header.number, header)));
+
+        let mut range = Range { from, to, blocks: vec![], parent_hash: H256::zero(), hashes: vec![] };
+        for (number, header) in headers {
+            if number == from {
+                range.parent_hash = header.parent_hash;
+            }
+            range.hashes.push((number, header.hash));
+
+            if let Some(logs) = logs.remove(&number) {
+                let mut data = BlockData::empty(number);
+                data.set_header(&header);
+                data.logs = logs;
+                range.blocks.push(data);
+            }
+        }
+        Ok(range)
+    }
+
+    async fn fetch_block(&self, fetch: &Fetch, block: U64) -> web3::Result<BlockData> {
+        let id = BlockId::Number(BlockNumber::from(block));
+        let mut data = BlockData::empty(block);
+
+        match fetch {
+            Fetch::Header => {
+                let header = Header::from(&self.web3.eth().block(id).await?.ok_or_else(|| missing_block(block))?);
+                data.set_header(&header);
+                self.headers.insert(header);
+            }
+            Fetch::Transactions | Fetch::Receipts | Fetch::Deployments => {
+                let full = self.web3.eth().block_with_txs(id).await?.ok_or_else(|| missing_block(block))?;
+                let header = Header::from(&full);
+                data.set_header(&header);
+                self.headers.insert(header);
+                data.transactions = full.transactions;
+                match fetch {
+                    Fetch::Receipts => data.receipts = self.fetch_receipts(data.hash, &data.transactions).await?,
+                    Fetch::Deployments => {
+                        // Usually none or a single one, not worth the receipts of the whole block.
+                        let creations: Vec<Transaction> = data.transactions.iter().filter(|tx| tx.to.is_none()).cloned().collect();
+                        if !creations.is_empty() {
+                            data.receipts = self.batch_receipts(&creations).await?;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Fetch::Logs(_) => unreachable!("logs are fetched per range")
+        }
+
+        Ok(data)
+    }
+
+    /// Receipts of the transactions of block `hash`, in the same order as `transactions`. Uses
+    /// `eth_getBlockReceipts` and falls back to one batch of `eth_getTransactionReceipt` calls per
+    /// block for nodes that do not support it.
+    async fn fetch_receipts(&self, hash: H256, transactions: &[Transaction]) -> web3::Result<Vec<TransactionReceipt>> {
+        if transactions.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let transport = self.web3.transport();
+        let receipts: Vec<TransactionReceipt> = if self.block_receipts.get() {
+            match transport.execute("eth_getBlockReceipts", vec![web3::helpers::serialize(&hash)]).await {
+                Ok(value) => serde_json::from_value::<Option<Vec<TransactionReceipt>>>(value)
+                    .map_err(|error| web3::Error::Decoder(error.to_string()))?
+                    .ok_or_else(|| web3::Error::InvalidResponse(format!("Missing receipts of block {:?}", hash)))?,
+                Err(web3::Error::Rpc(error)) if error.code == jsonrpc_core::ErrorCode::MethodNotFound => {
+                    println!("Node does not support eth_getBlockReceipts, fetching receipts in batches");
+                    self.block_receipts.set(false);
+                    return self.batch_receipts(transactions).await;
+                }
+                Err(error) => return Err(error)
+            }
+        } else {
+            return self.batch_receipts(transactions).await;
+        };
+
+        let mut receipts: HashMap<H256, TransactionReceipt> = receipts.into_iter().map(|receipt| (receipt.transaction_hash, receipt)).collect();
+        transactions.iter().map(|tx| missing_receipt(tx, receipts.remove(&tx.hash))).collect()
+    }
+
+    async fn batch_receipts(&self, transactions: &[Transaction]) -> web3::Result<Vec<TransactionReceipt>> {
+        let transport = self.web3.transport();
+        let requests = transactions.iter().map(|tx| transport.prepare("eth_getTransactionReceipt", vec![web3::helpers::serialize(&tx.hash)]));
+        let responses = transport.send_batch(requests).await?;
+
+        transactions.iter().zip(responses).map(|(tx, response)| {
+            let receipt = serde_json::from_value::<Option<TransactionReceipt>>(response?)
+                .map_err(|error| web3::Error::Decoder(error.to_string()))?;
+            missing_receipt(tx, receipt)
+        }).collect()
+    }
+
+    /// Walks back through the recently seen blocks until one still matches the canonical chain and
+    /// returns its number and hash. Mismatching entries are dropped from `recent`. If none matches,
+    /// e.g. because the checkpoint block was orphaned while the importer was stopped, the fork is
+    /// assumed to lie at most `depth` blocks below the oldest of them.
+    async fn find_fork(&self, recent: &mut VecDeque<(U64, H256)>, depth: usize) -> Result<(U64, H256)> {
+        let oldest = recent.front().map(|&(number, _)| number).unwrap_or_default();
+        while let Some((number, hash)) = recent.back().copied() {
+            let header = self.web3.eth().block(BlockId::Number(BlockNumber::from(number))).await?;
+            if header.and_then(|header| header.hash) == Some(hash) {
+                return Ok((number, hash));
+            }
+            recent.pop_back();
+        }
+
+        let fork = oldest.saturating_sub(U64::from(std::cmp::max(depth, 1)));
+        let header = self.web3.eth().block(BlockId::Number(BlockNumber::from(fork))).await?.ok_or_else(|| missing_block(fork))?;
+        let hash = header.hash.unwrap_or_default();
+        println!("None of the tracked block hashes is canonical anymore, rolling back {} blocks", oldest - fork);
+        recent.push_back((fork, hash));
+        Ok((fork, hash))
+    }
+}
+
+/// Object safe part of a [`BlockHandler`] plus its pending documents, so handlers with different
+/// document types can share one scanner.
+trait Stage {
+    fn name(&self) -> &str;
+    fn label(&self) -> &str;
+    /// Highest block in the handler's collection.
+    fn head_block(&self) -> Result<Option<U64>>;
+    /// Decodes a block and returns the number of documents it yielded.
+    fn decode(&mut self, block: &BlockData) -> Result<usize>;
+    fn pending(&self) -> usize;
+    fn persist(&mut self, db: &MongoDb) -> Result<WriteSummary>;
+    fn rollback(&self, block: U64) -> Result<()>;
+}
+
+struct HandlerStage<H: BlockHandler> {
+    handler: H,
+    pending: Vec<H::Document>,
+}
+
+impl<H: BlockHandler> Stage for HandlerStage<H> {
+    fn name(&self) -> &str {
+        self.handler.name()
+    }
+
+    fn label(&self) -> &str {
+        self.handler.label()
+    }
+
+    fn head_block(&self) -> Result<Option<U64>> {
+        get_db_head_block(self.handler.collection())
+    }
+
+    fn decode(&mut self, block: &BlockData) -> Result<usize> {
+        let mut documents = self.handler.decode(block)?;
+        let count = documents.len();
+        self.pending.append(&mut documents);
+        Ok(count)
+    }
+
+    fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn persist(&mut self, db: &MongoDb) -> Result<WriteSummary> {
+        self.handler.persist(db, std::mem::take(&mut self.pending))
+    }
+
+    fn rollback(&self, block: U64) -> Result<()> {
+        self.handler.rollback(block)
+    }
+}
+
+/// A [`BlockHandler`] registered with a [`Scanner`]. Every importer keeps its own checkpoint in
+/// its own database, so several importers can share one pass over the chain.
+pub struct Importer {
+    stage: Box<dyn Stage>,
+    db: MongoDb,
+    fetch: Fetch,
+    /// Start Block - 0 resumes from the checkpoint
+    start_block: u32,
+    batch_size: usize,
+    /// First block not decoded yet.
+    next: U64,
+}
+
+impl Importer {
+    /// Takes the start block and batch size of the importer from `options`.
+    pub fn new<H: BlockHandler + 'static>(handler: H, db: MongoDb, options: &ScannerOptions) -> Importer {
+        Importer {
+            fetch: handler.fetch(),
+            stage: Box::new(HandlerStage { handler, pending: vec![] }),
+            db,
+            start_block: options.start_block,
+            batch_size: options.batch_size,
+            next: U64::zero(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.stage.name()
+    }
+
+    pub fn db(&self) -> &MongoDb {
+        &self.db
+    }
+
+    /// Block to resume from: the block after the checkpoint, falling back to the highest block in
+    /// the data collection for imports that predate checkpoints.
+    fn resume_block(&self) -> Result<U64> {
+        let head = match self.db.get_checkpoint(self.stage.name())? {
+            Some(checkpoint) => Some(U64::from(checkpoint)),
+            None => self.stage.head_block()?
+        };
+        Ok(head.map(|head| head + 1).unwrap_or_default())
+    }
+
+    /// Hash of the checkpoint block, if it was stored with the checkpoint.
+    fn resume_hash(&self) -> Result<Option<H256>> {
+        Ok(self.db.get_checkpoint_hash(self.stage.name())?.and_then(|hash| hash.parse().ok()))
+    }
+
+    /// Flushes pending documents and removes everything after `fork` (with hash `hash`), if
+    /// anything was imported past it.
+    fn rollback(&mut self, fork: U64, hash: H256) -> Result<()> {
+        if self.next <= fork + 1 {
+            return Ok(());
+        }
+        if self.stage.pending() > 0 {
+            self.stage.persist(&self.db)?;
+        }
+        self.stage.rollback(fork)?;
+        self.db.update_checkpoint(self.stage.name(), fork.as_u64(), Some(&format!("{:?}", hash)))?;
+        self.next = fork + 1;
+        Ok(())
+    }
+}
+
+pub struct Scanner {
+    fetcher: Fetcher,
+    importers: Vec<Importer>,
+    options: ScannerOptions,
+    /// Hashes of recently processed blocks, used to detect chain reorganisations.
+    recent: VecDeque<(U64, H256)>,
+}
+
+impl Scanner {
+    /// Every block is fetched once and handed to all `importers`. The start block and batch size
+    /// are taken from each importer, everything else from `options`. Headers of blocks with logs
+    /// are resolved through `headers`.
+    pub fn new(web3: Web3<Transport>, headers: HeaderCache, importers: Vec<Importer>, options: ScannerOptions) -> Scanner {
+        Scanner {
+            fetcher: Fetcher { web3, window: Cell::new(1), block_receipts: Cell::new(true), headers },
+            importers,
+            options,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Resolves where every importer starts and returns the lowest of those blocks. When that block
+    /// continues a checkpoint with a known hash, the hash is tracked so the first range is checked
+    /// against it.
+    fn start_block(&mut self) -> Result<U64> {
+        for importer in &mut self.importers {
+            importer.next = if importer.start_block == 0 {
+                importer.resume_block()?
+            } else {
+                U64::from(importer.start_block)
+            };
+        }
+        let block = self.importers.iter().map(|importer| importer.next).min().unwrap_or_default();
+
+        self.recent.clear();
+        if let Some(checkpoint) = block.as_u64().checked_sub(1).map(U64::from) {
+            for importer in self.importers.iter().filter(|importer| importer.start_block == 0 && importer.next == block) {
+                if let Some(hash) = importer.resume_hash()? {
+                    self.recent.push_back((checkpoint, hash));
+                    break;
+                }
+            }
+        }
+        Ok(block)
+    }
+
+    pub fn importers(&self) -> &[Importer] {
+        &self.importers
+    }
+
+    /// Highest block that has at least `confirmations` blocks on top of it on every endpoint in rotation.
+    async fn safe_head(&self) -> Result<U64> {
+        let head = match self.fetcher.web3.transport().head().await {
+            Some(head) => head,
+            None => self.fetcher.web3.eth().block_number().await?
+        };
+        Ok(head.saturating_sub(U64::from(self.options.confirmations)))
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        let block = self.start_block()?;
+
+        let max_block = if self.options.end_block == 0 {
+            self.safe_head().await?
+        } else {
+            U64::from(self.options.end_block)
+        };
+
+        println!("Effective start_block: {}", block);
+        println!("Effective end_block: {}", max_block);
+
+        let mut block = self.import(block, max_block).await?;
+
+        if self.options.follow && self.options.end_block == 0 {
+            println!("Caught up with the chain, following new blocks");
+            let mut heads = self.subscribe_new_heads().await;
+            loop {
+                let max_block = self.wait_for_block(&mut heads, block).await?;
+                block = self.import(block, max_block).await?;
+            }
+        }
+
+        println!("Breaking!");
+
+        Ok(())
+    }
+
+    /// Subscribes to new heads if the node is connected through a WebSocket, HTTP connections poll instead.
+    async fn subscribe_new_heads(&self) -> Option<SubscriptionStream<WebSocket, BlockHeader>> {
+        let ws = self.fetcher.web3.transport().websocket().await?;
+        Web3::new(ws).eth_subscribe().subscribe_new_heads().await.ok()
+    }
+
+    /// Waits until `block` has enough confirmations and returns the new highest block to import.
+    async fn wait_for_block(&self, heads: &mut Option<SubscriptionStream<WebSocket, BlockHeader>>, block: U64) -> Result<U64> {
+        loop {
+            let safe_head = self.safe_head().await?;
+            if safe_head >= block {
+                return Ok(safe_head);
+            }
+
+            match heads {
+                Some(subscription) => {
+                    if subscription.next().await.is_none() {
+                        println!("New heads subscription closed, falling back to polling");
+                        *heads = None;
+                    }
+                }
+                None => tokio::time::sleep(self.options.poll_interval).await
+            }
+        }
+    }
+
+    /// Imports all blocks from `block` to `max_block` and returns the next block to import.
+    async fn import(&mut self, mut block: U64, max_block: U64) -> Result<U64> {
+        if block > max_block {
+            return Ok(block);
+        }
+
+        let plan = Plan::new(self.importers.iter().map(|importer| &importer.fetch));
+        let is_logs = plan.blocks.is_none();
+        let shared = self.importers.len() > 1;
+        let fetcher = &self.fetcher;
+
+        // Restarted from the fork point whenever a chain reorganisation is detected.
+        'scan: while block <= max_block {
+            // Ranges are cut lazily so that every new range picks up the current window size.
+            let ranges = stream::unfold(block, |from| async move {
+                if from > max_block {
+                    return None;
+                }
+                let to = if is_logs { std::cmp::min(from + fetcher.window.get() - 1, max_block) } else { from };
+                Some(((from, to), to + 1))
+            });
+
+            // `buffered` keeps up to `concurrency` ranges in flight but yields them in block order,
+            // so documents are always persisted in ascending block order.
+            let mut results = ranges
+                .map(|(from, to)| {
+                    let plan = &plan;
+                    async move { fetcher.fetch_range(plan, from, to).await.map_err(|error| Error::from(error).in_blocks(from, to)) }
+                })
+                .buffered(std::cmp::max(self.options.concurrency, 1))
+                .boxed_local();
+
+            while let Some(range) = results.next().await {
+                let range = range?;
+                let (from, to) = (range.from, range.to);
+
+                if let Some(&(_, hash)) = self.recent.back() {
+                    if range.parent_hash != hash {
+                        let (fork, fork_hash) = fetcher.find_fork(&mut self.recent, self.options.reorg_depth).await?;
+                        fetcher.headers.forget_after(fork);
+                        for importer in &mut self.importers {
+                            importer.rollback(fork, fork_hash)?;
+                        }
+                        println!("Chain reorganisation at block {}, rolled back to block {}", from, fork);
+                        block = fork + 1;
+                        continue 'scan;
+                    }
+                }
+
+                let mut counts = vec![];
+                for importer in self.importers.iter_mut().filter(|importer| to >= importer.next) {
+                    let mut count = 0;
+                    for data in range.blocks.iter().filter(|data| data.number >= importer.next) {
+                        let decoded = if shared {
+                            importer.stage.decode(&data.select(&importer.fetch))
+                        } else {
+                            importer.stage.decode(data)
+                        };
+                        count += decoded.map_err(|error| error.in_blocks(data.number, data.number))?;
+                    }
+                    importer.next = to + 1;
+
+                    let pending = importer.stage.pending();
+                    let mut summary = WriteSummary::default();
+                    if pending > 0 && (pending >= importer.batch_size || to == max_block) {
+                        summary = importer.stage.persist(&importer.db).map_err(|error| error.in_blocks(from, to))?;
+                    }
+
+                    // Everything up to `to` is in the database once nothing is left pending.
+                    if importer.stage.pending() == 0 {
+                        let hash = range.hashes.last().map(|(_, hash)| format!("{:?}", hash));
+                        importer.db.update_checkpoint(importer.stage.name(), to.as_u64(), hash.as_deref())?;
+                    }
+
+                    if self.options.follow {
+                        importer.db.update_health(importer.stage.name().into());
+                    }
+
+                    let duplicates = if summary.duplicates > 0 { format!("\tDuplicates: {}", summary.duplicates) } else { String::new() };
+                    counts.push(format!("{}: {}{}", importer.stage.label(), count, duplicates));
+                }
+
+                let completion: f32 = (to.as_u64() as f32 / max_block.as_u64() as f32) * 100f32;
+                if to == from {
+                    println!("Block: {}\t{} ({:.6}%)", from, counts.join("\t"), completion);
+                } else {
+                    println!("Blocks: {} - {}\t{} ({:.6}%)", from, to, counts.join("\t"), completion);
+                }
+
+                if is_logs {
+                    let logs: usize = range.blocks.iter().map(|data| data.logs.len()).sum();
+                    let window = fetcher.window.get();
+                    if logs < self.options.target_logs / 2 {
+                        fetcher.window.set(std::cmp::min(window * 2, self.options.max_window));
+                    } else if logs > self.options.target_logs {
+                        fetcher.window.set(std::cmp::max(window / 2, 1));
+                    }
+                }
+
+                self.recent.extend(range.hashes);
+                while self.recent.len() > self.options.reorg_depth {
+                    self.recent.pop_front();
+                }
+            }
+
+            break;
+        }
+
+        Ok(max_block + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use clap::Parser;
+    use jsonrpc_core::ErrorCode;
+
+    use super::{is_range_too_large, CommonArgs};
+    use crate::config::Config;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        common: CommonArgs,
+    }
+
+    /// Config of the importers `names` for the command line `args` and the config file `file`.
+    fn config(args: &[&str], file: &str, names: &[&str]) -> Config {
+        static FILES: AtomicUsize = AtomicUsize::new(0);
+        let id = FILES.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("ronin-importer-test-{}-{}.toml", std::process::id(), id));
+        std::fs::write(&path, file).unwrap();
+        let path = path.to_string_lossy().to_string();
+        let args = ["ronin-importer", "--config", path.as_str()].into_iter().chain(args.iter().copied());
+        let config = Cli::parse_from(args).common.importers_config(names).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        config
+    }
+
+    fn rpc_error(code: i64, message: &str) -> web3::Error {
+        web3::Error::Rpc(jsonrpc_core::Error { code: ErrorCode::ServerError(code), message: message.to_string(), data: None })
+    }
+
+    #[test]
+    fn range_errors_shrink_the_window() {
+        assert!(is_range_too_large(&rpc_error(-32005, "query returned more than 10000 results")));
+        assert!(is_range_too_large(&rpc_error(-32000, "Response size exceeded")));
+        assert!(is_range_too_large(&rpc_error(-32600, "block range is too wide")));
+        assert!(is_range_too_large(&rpc_error(-32000, "exceed maximum block range: 5000")));
+    }
+
+    #[test]
+    fn rate_limits_do_not_shrink_the_window() {
+        assert!(!is_range_too_large(&rpc_error(-32005, "limit exceeded")));
+        assert!(!is_range_too_large(&rpc_error(-32005, "daily request count exceeded, request rate limited")));
+        assert!(!is_range_too_large(&rpc_error(429, "Too many requests")));
+    }
+
+    #[test]
+    fn other_errors_do_not_shrink_the_window() {
+        assert!(!is_range_too_large(&rpc_error(-32000, "execution reverted")));
+        assert!(!is_range_too_large(&web3::Error::Transport("connection closed".to_string())));
+    }
+
+    #[test]
+    fn flags_win_over_the_config_file() {
+        let file = "[mongodb]\nname = \"file\"\n[importers.transactions]\nstart_block = 5\nbatch_size = 7\n";
+        let config = config(&["--start-block", "9"], file, &["transactions"]);
+        assert_eq!(config.scanner_options("transactions").start_block, 9);
+        assert_eq!(config.scanner_options("transactions").batch_size, 7);
+        assert_eq!(config.database_options("transactions").database, "file");
+    }
+
+    #[test]
+    fn config_file_wins_over_the_defaults() {
+        let config = config(&[], "[importers.block-stats]\ndatabase = \"stats\"\n", &["block-stats"]);
+        assert_eq!(config.database_options("block-stats").database, "stats");
+        assert_eq!(config.database_options("transactions").database, "ronin");
+        assert_eq!(config.scanner_options("block-stats").confirmations, 50);
+    }
+
+    #[test]
+    fn database_flag_applies_to_a_single_importer() {
+        let config = config(&["--mongodb-name", "other"], "", &["block-stats"]);
+        assert_eq!(config.database_options("block-stats").database, "other");
+    }
+
+    #[test]
+    fn shared_scan_keeps_importer_databases_and_collections() {
+        let config = config(&["--mongodb-name", "other", "--mongodb-collection", "mine"], "", &["blocks", "block-stats"]);
+        assert_eq!(config.database_options("blocks").database, "other");
+        assert_eq!(config.database_options("block-stats").database, "roninstatistics");
+        assert_eq!(config.collection("blocks"), "blocks");
+        assert_eq!(config.collection("block-stats"), "blocks");
+    }
+}
@@ -45,6 +45,8 @@ pub mod origin {
     pub mod leaderboard {
         use serde::Deserialize;
 
+        use crate::error::Result;
+
         #[derive(Deserialize)]
         struct Leaderboard {
             _items: Vec<LeaderboardItem>,
@@ -52,47 +54,49 @@ pub mod origin {
 
         #[derive(Deserialize, Clone)]
         pub struct LeaderboardItem {
-            pub userID: String,
+            #[serde(rename = "userID")]
+            pub user_id: String,
             pub name: String,
             pub rank: String,
             pub tier: u32,
-            pub topRank: u32,
+            #[serde(rename = "topRank")]
+            pub top_rank: u32,
             pub vstar: u32,
         }
 
-        pub async fn get_leaderboard_page(page: u32) -> Vec<LeaderboardItem> {
-            let offset = if page <= 0 { 0 } else { page - 1 } * 99;
+        pub async fn get_leaderboard_page(page: u32) -> Result<Vec<LeaderboardItem>> {
+            let offset = if page == 0 { 0 } else { page - 1 } * 99;
 
             let mut request_url = "https://game-api-origin.skymavis.com/v2/season-leaderboards?limit=100&offset=".to_owned();
             request_url.push_str(&offset.to_string());
 
             let client = reqwest::Client::new();
-            let result: Result<Leaderboard, reqwest::Error> = client.get(request_url)
+            let result: Leaderboard = client.get(request_url)
                 .header("User-Agent", "")
                 .send()
-                .await
-                .unwrap()
-                .json().await;
-            let mut items = result.unwrap()._items;
+                .await?
+                .json().await?;
+            let mut items = result._items;
             items.retain(|i| {
-                i.userID != "1ec9eb6f-896c-682f-a60c-19f2a53791d9".to_string()
+                i.user_id != "1ec9eb6f-896c-682f-a60c-19f2a53791d9"
             });
-            items
+            Ok(items)
         }
     }
 }
 
 pub mod database {
-    use mongodb::bson::{DateTime, doc};
-    use mongodb::options::{FindOneAndUpdateOptions, InsertOneOptions};
+    use mongodb::bson::{self, Bson, DateTime, doc};
+    use mongodb::error::{ErrorKind, WriteError, WriteFailure};
+    use mongodb::options::{FindOneAndUpdateOptions, IndexOptions, InsertManyOptions, UpdateOptions};
     use mongodb::sync::{Client, Collection};
+    use mongodb::IndexModel;
     use serde::{Deserialize, Serialize};
 
+    use crate::error::{Error, Result};
     use crate::tools::database::types::*;
 
     pub mod types {
-        use std::collections::HashMap;
-
         pub type ClientUri = String;
         pub type Database = String;
         pub type ServiceName = String;
@@ -104,25 +108,151 @@ pub mod database {
         pub last_active: DateTime,
     }
 
+    /// Last block an importer has fully persisted. Only advanced after all documents up to
+    /// and including `block` were written, so resuming from `block + 1` never leaves gaps.
+    #[derive(Serialize, Deserialize)]
+    pub struct Checkpoint {
+        pub name: ServiceName,
+        pub block: i64,
+        /// Hash of `block`, so a chain reorganisation while the importer was stopped is noticed
+        /// on resume. Missing for checkpoints written by older versions.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub hash: Option<String>,
+        pub updated_at: DateTime,
+    }
+
+    /// Server error code for a write rejected by a unique index.
+    const DUPLICATE_KEY: i32 = 11000;
+
+    /// Maximum number of upserts sent in a single `update` command.
+    const UPSERT_BATCH: usize = 1000;
+
+    /// Outcome of writing a batch of documents.
+    #[derive(Default, Clone, Copy, Debug)]
+    pub struct WriteSummary {
+        pub inserted: usize,
+        /// Documents that were already in the database because they were imported before.
+        pub duplicates: usize,
+    }
+
+    impl std::ops::AddAssign for WriteSummary {
+        fn add_assign(&mut self, other: WriteSummary) {
+            self.inserted += other.inserted;
+            self.duplicates += other.duplicates;
+        }
+    }
+
+    /// Inserts `documents` unordered. Duplicate key errors only mean that a document was already
+    /// imported, they are counted and ignored. Any other write error fails the whole batch.
+    pub fn insert_unordered<T: Serialize>(collection: &Collection<T>, documents: Vec<T>) -> Result<WriteSummary> {
+        if documents.is_empty() {
+            return Ok(WriteSummary::default());
+        }
+
+        let total = documents.len();
+        let options = InsertManyOptions::builder().ordered(false).build();
+        let error = match collection.insert_many(documents, options) {
+            Ok(result) => return Ok(WriteSummary { inserted: result.inserted_ids.len(), duplicates: 0 }),
+            Err(error) => error
+        };
+
+        if let ErrorKind::BulkWrite(failure) = &*error.kind {
+            if let (Some(errors), None) = (&failure.write_errors, &failure.write_concern_error) {
+                if errors.iter().all(|error| error.code == DUPLICATE_KEY) {
+                    return Ok(WriteSummary { inserted: total - errors.len(), duplicates: errors.len() });
+                }
+            }
+        }
+        Err(error.into())
+    }
+
+    /// Creates a unique index on `key`. Fails with [`Error::Config`] if the collection already holds
+    /// duplicates of `key`, e.g. from an import that predates the index, as restarting cannot help.
+    pub fn create_unique_index<T>(collection: &Collection<T>, key: &str) -> Result<()> {
+        let options = IndexOptions::builder().unique(true).build();
+        match collection.create_index(IndexModel::builder().keys(doc! {key: 1u32}).options(options).build(), None) {
+            Ok(_) => Ok(()),
+            Err(error) if matches!(&*error.kind, ErrorKind::Command(command) if command.code == DUPLICATE_KEY) => Err(Error::Config(format!(
+                "{} holds duplicate `{}` values, remove them before restarting (see \"Upgrading\" in the Readme)",
+                collection.namespace(), key
+            ))),
+            Err(error) => Err(error.into())
+        }
+    }
+
+    /// Drops the index `name` if it exists and is unique, e.g. a unique index on a key that was
+    /// replaced by a better one.
+    pub fn drop_unique_index<T>(collection: &Collection<T>, name: &str) -> Result<()> {
+        let indexes: Vec<IndexModel> = collection.list_indexes(None)?.collect::<mongodb::error::Result<_>>()?;
+        for index in indexes {
+            let options = index.options.unwrap_or_default();
+            if options.name.as_deref() == Some(name) && options.unique == Some(true) {
+                collection.drop_index(name, None)?;
+            }
+        }
+        Ok(())
+    }
+
     pub struct Options {
         pub client_uri: ClientUri,
         pub database: Database,
     }
 
+    #[derive(Clone)]
     pub struct MongoDb {
         pub client: Client,
         pub database: mongodb::sync::Database,
     }
 
     impl MongoDb {
-        pub async fn new(options: Options) -> MongoDb {
-            let client = Client::with_uri_str(&options.client_uri).unwrap();
+        /// Fails with [`Error::Config`] if the connection URL is invalid.
+        pub async fn new(options: Options) -> Result<MongoDb> {
+            let client = Client::with_uri_str(&options.client_uri)
+                .map_err(|error| Error::Config(format!("Invalid MongoDB URL: {}", error)))?;
             let database = client.database(&options.database);
 
-            MongoDb {
+            Ok(MongoDb {
                 client,
                 database,
+            })
+        }
+
+        /// Writes `documents` as upserts matched on their `key` field, so writing the same document
+        /// twice leaves a single copy. Documents that already existed are counted as duplicates.
+        pub fn upsert_many<T: Serialize>(&self, collection: &Collection<T>, key: &str, documents: Vec<T>) -> Result<WriteSummary> {
+            let mut summary = WriteSummary::default();
+
+            for chunk in documents.chunks(UPSERT_BATCH) {
+                let mut updates = vec![];
+                for document in chunk {
+                    let document = bson::to_document(document).map_err(mongodb::error::Error::from)?;
+                    let value = document.get(key).cloned().unwrap_or(Bson::Null);
+                    updates.push(doc! {"q": {key: value}, "u": document, "upsert": true});
+                }
+
+                let response = self.database.run_command(doc! {
+                    "update": collection.name(),
+                    "updates": updates,
+                    "ordered": false
+                }, None)?;
+
+                // Two concurrent upserts of the same key can race into a duplicate key error; the
+                // document exists either way.
+                for error in response.get_array("writeErrors").map(|errors| errors.to_vec()).unwrap_or_default() {
+                    let error: WriteError = match error {
+                        Bson::Document(error) => bson::from_document(error).map_err(mongodb::error::Error::from)?,
+                        _ => continue
+                    };
+                    if error.code != DUPLICATE_KEY {
+                        return Err(mongodb::error::Error::from(ErrorKind::Write(WriteFailure::WriteError(error))).into());
+                    }
+                }
+
+                let upserted = response.get_array("upserted").map(Vec::len).unwrap_or(0);
+                summary += WriteSummary { inserted: upserted, duplicates: chunk.len() - upserted };
             }
+
+            Ok(summary)
         }
 
         pub fn health_collection(&self) -> Collection<ServiceHealth> {
@@ -144,6 +274,46 @@ pub mod database {
             ).ok();
             true
         }
+
+        pub fn checkpoint_collection(&self) -> Collection<Checkpoint> {
+            self.database.collection("checkpoints")
+        }
+
+        pub fn get_checkpoint(&self, name: &str) -> Result<Option<u64>> {
+            let col = self.checkpoint_collection();
+            let checkpoint = col.find_one(doc! {"name": name}, None)?;
+            Ok(checkpoint.map(|checkpoint| checkpoint.block as u64))
+        }
+
+        /// Hash stored with the checkpoint of `name`, if any.
+        pub fn get_checkpoint_hash(&self, name: &str) -> Result<Option<String>> {
+            let col = self.checkpoint_collection();
+            let checkpoint = col.find_one(doc! {"name": name}, None)?;
+            Ok(checkpoint.and_then(|checkpoint| checkpoint.hash))
+        }
+
+        /// Moves the checkpoint of `name` to `block`. A stale hash is removed when `hash` is unknown.
+        pub fn update_checkpoint(&self, name: &str, block: u64, hash: Option<&str>) -> Result<()> {
+            let col = self.checkpoint_collection();
+            let opt = UpdateOptions::builder().upsert(Some(true)).build();
+            let mut set = doc! {
+                "name": name,
+                "block": block as i64,
+                "updated_at": DateTime::from_millis(chrono::Utc::now().timestamp() * 1000)
+            };
+            let mut update = doc! {};
+            match hash {
+                Some(hash) => {
+                    set.insert("hash", hash);
+                }
+                None => {
+                    update.insert("$unset", doc! {"hash": ""});
+                }
+            }
+            update.insert("$set", set);
+            col.update_one(doc! {"name": name}, update, opt)?;
+            Ok(())
+        }
     }
 }
 
@@ -201,8 +371,10 @@ pub mod types {
     pub enum ItemId {
         Exp,
         Moonshard,
-        Feature_Mailbox,
-        Feature_News,
+        #[serde(rename = "feature_mailbox")]
+        FeatureMailbox,
+        #[serde(rename = "feature_news")]
+        FeatureNews,
         Slp
     }
 
@@ -0,0 +1,379 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::future::Future;
+use std::time::Duration;
+
+use futures::future::{join_all, BoxFuture, FutureExt};
+use tokio::sync::Mutex;
+use web3::transports::{Either, Http, WebSocket};
+use web3::types::U64;
+use web3::{helpers, BatchTransport, RequestId, Transport, Web3};
+
+use crate::error::{Error, Result};
+
+/// How RPC calls are spread over the endpoints and how failed calls are retried.
+#[derive(Clone, Debug)]
+pub struct TransportOptions {
+    /// Number of retries before a call is given up.
+    pub retries: u32,
+    /// Delay before the first retry. Doubled after every further failure.
+    pub backoff: Duration,
+    /// Upper bound for the delay between two retries.
+    pub max_backoff: Duration,
+    /// Time after which a call without response counts as failed.
+    pub timeout: Duration,
+    /// Number of blocks an endpoint may fall behind the best endpoint before it is taken out of rotation.
+    pub max_lag: u64,
+    /// How often the endpoints are health checked.
+    pub health_interval: Duration,
+}
+
+impl Default for TransportOptions {
+    fn default() -> Self {
+        TransportOptions {
+            retries: 5,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            timeout: Duration::from_secs(60),
+            max_lag: 10,
+            health_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Failures of the connection itself and rate limits are worth retrying, other errors returned by
+/// the node are final.
+fn is_retryable(error: &web3::Error) -> bool {
+    match error {
+        web3::Error::Transport(_) | web3::Error::Io(_) | web3::Error::Unreachable => true,
+        web3::Error::Rpc(error) => is_rate_limit(error),
+        _ => false
+    }
+}
+
+pub fn is_rate_limit(error: &jsonrpc_core::Error) -> bool {
+    let message = error.message.to_lowercase();
+    error.code.code() == 429 || message.contains("rate limit") || message.contains("too many requests") || message.contains("rate exceeded")
+}
+
+/// Calls for a block the scanner has already seen to exist, a `null` result means the endpoint is behind.
+const BLOCK_METHODS: [&str; 3] = ["eth_getBlockByNumber", "eth_getBlockByHash", "eth_getBlockReceipts"];
+
+fn method_name(request: &jsonrpc_core::Call) -> &str {
+    match request {
+        jsonrpc_core::Call::MethodCall(call) => &call.method,
+        jsonrpc_core::Call::Notification(notification) => &notification.method,
+        jsonrpc_core::Call::Invalid { .. } => "invalid"
+    }
+}
+
+/// Picks the provider type from the URL scheme, falling back to `default` for anything else.
+fn resolve_provider_type(hostname: &str, default: &str) -> Result<&'static str> {
+    if hostname.starts_with("ws://") || hostname.starts_with("wss://") {
+        Ok("ws")
+    } else if hostname.starts_with("http://") || hostname.starts_with("https://") {
+        Ok("http")
+    } else {
+        match default {
+            "ws" => Ok("ws"),
+            "http" => Ok("http"),
+            _ => Err(Error::Config(format!("Invalid provider type: {}", default)))
+        }
+    }
+}
+
+async fn open(hostname: &str, provider_type: &'static str) -> web3::Result<Either<WebSocket, Http>> {
+    match provider_type {
+        "ws" => Ok(Either::Left(WebSocket::new(hostname).await?)),
+        "http" => Ok(Either::Right(Http::new(hostname)?)),
+        _ => unreachable!("provider types are validated by resolve_provider_type()")
+    }
+}
+
+struct Connection {
+    /// Incremented on every reconnect, so concurrent failures only reconnect once.
+    generation: u64,
+    /// `None` while the endpoint could not be reached.
+    transport: Option<Either<WebSocket, Http>>,
+}
+
+struct Endpoint {
+    hostname: String,
+    provider_type: &'static str,
+    connection: Mutex<Connection>,
+    healthy: AtomicBool,
+}
+
+impl Endpoint {
+    async fn current(&self) -> (u64, Option<Either<WebSocket, Http>>) {
+        let connection = self.connection.lock().await;
+        (connection.generation, connection.transport.clone())
+    }
+
+    /// Replaces a connection that failed, unless another call already did.
+    async fn reconnect(&self, generation: u64) {
+        let mut connection = self.connection.lock().await;
+        if connection.generation != generation || (connection.transport.is_some() && self.provider_type != "ws") {
+            return;
+        }
+        match open(&self.hostname, self.provider_type).await {
+            Ok(transport) => {
+                println!("Connected to {}", self.hostname);
+                connection.transport = Some(transport);
+                connection.generation += 1;
+            }
+            Err(error) => println!("Connecting to {} failed: {}", self.hostname, error)
+        }
+    }
+
+    async fn block_number(&self, timeout: Duration) -> web3::Result<U64> {
+        let (generation, transport) = self.current().await;
+        let transport = match transport {
+            Some(transport) => transport,
+            None => {
+                self.reconnect(generation).await;
+                self.current().await.1.ok_or(web3::Error::Unreachable)?
+            }
+        };
+        match tokio::time::timeout(timeout, Web3::new(transport).eth().block_number()).await {
+            Ok(result) => result,
+            Err(_) => Err(web3::Error::Transport(format!("No response within {:?}", timeout)))
+        }
+    }
+}
+
+struct Shared {
+    endpoints: Vec<Endpoint>,
+    options: TransportOptions,
+    id: AtomicUsize,
+    /// Round robin position over the endpoints.
+    next: AtomicUsize,
+}
+
+impl Shared {
+    /// Next endpoint in rotation, skipping unhealthy ones as long as a healthy endpoint is left.
+    fn pick(&self) -> &Endpoint {
+        let count = self.endpoints.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..count)
+            .map(|offset| &self.endpoints[(start + offset) % count])
+            .find(|endpoint| endpoint.healthy.load(Ordering::Relaxed))
+            .unwrap_or(&self.endpoints[start % count])
+    }
+
+    /// Compares the chain heads of all endpoints and takes failing or lagging ones out of rotation.
+    /// Returns the lowest head among the endpoints left in rotation.
+    async fn check_health(&self) -> Option<U64> {
+        let heads = join_all(self.endpoints.iter().map(|endpoint| endpoint.block_number(self.options.timeout))).await;
+        let best = heads.iter().filter_map(|head| head.as_ref().ok()).max().copied();
+        let mut lowest: Option<U64> = None;
+
+        for (endpoint, head) in self.endpoints.iter().zip(heads) {
+            let healthy = match (&head, best) {
+                (Ok(head), Some(best)) => best.saturating_sub(*head) <= U64::from(self.options.max_lag),
+                _ => false
+            };
+            if let (true, Ok(head)) = (healthy, &head) {
+                lowest = Some(lowest.map_or(*head, |lowest| std::cmp::min(lowest, *head)));
+            }
+            if healthy != endpoint.healthy.swap(healthy, Ordering::Relaxed) {
+                match head {
+                    Ok(head) if healthy => println!("Endpoint {} is back at block {}", endpoint.hostname, head),
+                    Ok(head) => println!("Endpoint {} fell behind at block {}, taking it out of rotation", endpoint.hostname, head),
+                    Err(error) => println!("Endpoint {} failed its health check: {}", endpoint.hostname, error)
+                }
+            }
+        }
+
+        lowest
+    }
+
+    async fn send(&self, id: RequestId, request: jsonrpc_core::Call) -> web3::Result<jsonrpc_core::Value> {
+        let method = method_name(&request);
+        let requires_block = BLOCK_METHODS.contains(&method);
+        self.retry(method, |transport| {
+            let response = transport.send(id, request.clone());
+            async move {
+                match response.await {
+                    // An endpoint that is behind does not know the block yet, another one may.
+                    Ok(jsonrpc_core::Value::Null) if requires_block => Err(web3::Error::Transport("Block not found".to_string())),
+                    result => result
+                }
+            }
+        }).await
+    }
+
+    async fn send_batch(&self, requests: Vec<(RequestId, jsonrpc_core::Call)>) -> web3::Result<Vec<web3::Result<jsonrpc_core::Value>>> {
+        let method = format!("Batch of {} calls", requests.len());
+        self.retry(&method, |transport| transport.send_batch(requests.clone())).await
+    }
+
+    /// Runs `call` on the next endpoint in rotation, failing over to the other endpoints and
+    /// retrying with backoff as long as the connection fails.
+    async fn retry<T, F, Fut>(&self, method: &str, call: F) -> web3::Result<T>
+    where
+        F: Fn(Either<WebSocket, Http>) -> Fut,
+        Fut: Future<Output = web3::Result<T>>,
+    {
+        let mut backoff = self.options.backoff;
+        let mut attempt = 0;
+
+        loop {
+            let endpoint = self.pick();
+            let (generation, transport) = endpoint.current().await;
+            let result = match transport {
+                Some(transport) => match tokio::time::timeout(self.options.timeout, call(transport)).await {
+                    Ok(result) => result,
+                    Err(_) => Err(web3::Error::Transport(format!("No response within {:?}", self.options.timeout)))
+                },
+                None => Err(web3::Error::Unreachable)
+            };
+
+            match result {
+                Err(error) if is_retryable(&error) => {
+                    if attempt >= self.options.retries {
+                        return Err(web3::Error::Transport(format!(
+                            "{} failed after {} attempts: {}", method, attempt + 1, error
+                        )));
+                    }
+                    attempt += 1;
+                    println!("{} on {} failed ({}), retrying ({}/{})", method, endpoint.hostname, error, attempt, self.options.retries);
+                    if self.endpoints.len() > 1 {
+                        endpoint.healthy.store(false, Ordering::Relaxed);
+                    }
+                    // Fail over to the other endpoints right away, back off once all of them failed.
+                    if (attempt as usize).is_multiple_of(self.endpoints.len()) {
+                        tokio::time::sleep(backoff).await;
+                        backoff = std::cmp::min(backoff * 2, self.options.max_backoff);
+                    }
+                    endpoint.reconnect(generation).await;
+                }
+                result => return result
+            }
+        }
+    }
+}
+
+/// Spreads RPC calls over one or more WebSocket/HTTP endpoints, retrying failed calls with
+/// exponential backoff, re-establishing dropped connections and taking endpoints that error or
+/// fall behind out of rotation.
+#[derive(Clone)]
+pub struct ResilientTransport {
+    shared: Arc<Shared>,
+}
+
+impl std::fmt::Debug for ResilientTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hostnames: Vec<&str> = self.shared.endpoints.iter().map(|endpoint| endpoint.hostname.as_str()).collect();
+        f.debug_struct("ResilientTransport").field("endpoints", &hostnames).finish()
+    }
+}
+
+impl ResilientTransport {
+    /// Connects to all `hostnames`. The provider type is taken from the URL scheme, `provider_type`
+    /// is used for hostnames without one. Fails only if none of the endpoints can be reached.
+    pub async fn new(hostnames: &[String], provider_type: &str, options: TransportOptions) -> Result<ResilientTransport> {
+        if hostnames.is_empty() {
+            return Err(Error::Config("No web3 endpoint configured".to_string()));
+        }
+
+        let mut endpoints = vec![];
+        let mut last_error = None;
+        for hostname in hostnames {
+            let provider_type = resolve_provider_type(hostname, provider_type)?;
+            let transport = match open(hostname, provider_type).await {
+                Ok(transport) => Some(transport),
+                Err(error) => {
+                    println!("Connecting to {} failed: {}", hostname, error);
+                    last_error = Some(error);
+                    None
+                }
+            };
+            endpoints.push(Endpoint {
+                hostname: hostname.to_string(),
+                provider_type,
+                healthy: AtomicBool::new(transport.is_some()),
+                connection: Mutex::new(Connection { generation: 0, transport }),
+            });
+        }
+
+        if !endpoints.iter().any(|endpoint| endpoint.healthy.load(Ordering::Relaxed)) {
+            return Err(last_error.unwrap_or(web3::Error::Unreachable).into());
+        }
+
+        let shared = Arc::new(Shared {
+            endpoints,
+            options,
+            id: AtomicUsize::new(1),
+            next: AtomicUsize::new(0),
+        });
+
+        if shared.endpoints.len() > 1 {
+            shared.check_health().await;
+            tokio::spawn(health_check(Arc::downgrade(&shared)));
+        }
+
+        Ok(ResilientTransport { shared })
+    }
+
+    /// Chain head every endpoint in rotation has reached, so ranges up to it are complete on
+    /// whichever endpoint they are sent to. Refreshes the health of the endpoints on the way.
+    /// `None` if no endpoint answered.
+    pub async fn head(&self) -> Option<U64> {
+        self.shared.check_health().await
+    }
+
+    /// A WebSocket connection to a healthy endpoint, if any endpoint is connected through one.
+    pub async fn websocket(&self) -> Option<WebSocket> {
+        for endpoint in self.shared.endpoints.iter().filter(|endpoint| endpoint.healthy.load(Ordering::Relaxed)) {
+            if let (_, Some(Either::Left(ws))) = endpoint.current().await {
+                return Some(ws);
+            }
+        }
+        None
+    }
+}
+
+/// Periodically health checks the endpoints until the transport is dropped.
+async fn health_check(shared: Weak<Shared>) {
+    loop {
+        let interval = match shared.upgrade() {
+            Some(shared) => shared.options.health_interval,
+            None => return
+        };
+        tokio::time::sleep(interval).await;
+        match shared.upgrade() {
+            Some(shared) => {
+                shared.check_health().await;
+            }
+            None => return
+        }
+    }
+}
+
+impl Transport for ResilientTransport {
+    type Out = BoxFuture<'static, web3::Result<jsonrpc_core::Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<jsonrpc_core::Value>) -> (RequestId, jsonrpc_core::Call) {
+        let id = self.shared.id.fetch_add(1, Ordering::AcqRel);
+        (id, helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, id: RequestId, request: jsonrpc_core::Call) -> Self::Out {
+        let shared = self.shared.clone();
+        async move { shared.send(id, request).await }.boxed()
+    }
+}
+
+impl BatchTransport for ResilientTransport {
+    type Batch = BoxFuture<'static, web3::Result<Vec<web3::Result<jsonrpc_core::Value>>>>;
+
+    fn send_batch<T>(&self, requests: T) -> Self::Batch
+    where
+        T: IntoIterator<Item = (RequestId, jsonrpc_core::Call)>,
+    {
+        let shared = self.shared.clone();
+        let requests: Vec<_> = requests.into_iter().collect();
+        async move { shared.send_batch(requests).await }.boxed()
+    }
+}